@@ -0,0 +1,789 @@
+/*
+ *  Tamper-evident audit log of clock traceability for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use hmac::{ Hmac, Mac };
+use serde::{ Deserialize, Serialize };
+use sha2::{ Digest, Sha256 };
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{ BufRead, Read, Seek, SeekFrom, Write },
+    os::fd::AsRawFd,
+    path::Path,
+    sync::{ Arc, Mutex } };
+use crate::{
+    OffsetEvent, Timestamp,
+    config::SyncConfig,
+    ntp::{ NtpError, NtpExchange },
+    sync::TimeSource };
+
+
+/// The means by which each record's hash is computed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MacKind {
+    /// Plain SHA-256, which detects accidental or naive alterations
+    Sha256,
+
+    /// HMAC-SHA256 with a secret key, which cannot be recomputed without that key
+    HmacSha256
+}
+
+
+/// The latest response from one NTP server within the interval covered by a record
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SourceRecord {
+    pub server: String,
+    pub address: String,
+    pub stratum: u8,
+    pub ref_id: String,
+
+    /// The measured correction to the local clock, in seconds
+    pub offset: f64,
+
+    /// The round-trip time, in seconds
+    pub rtt: f64
+}
+
+impl SourceRecord {
+    fn new(exch: &NtpExchange) -> SourceRecord {
+        SourceRecord {
+            server: exch.server.clone(),
+            address: exch.address.to_string(),
+            stratum: exch.stratum,
+            ref_id: exch.ref_id_string(),
+            offset: exch.offset(),
+            rtt: exch.roundtrip() }
+    }
+}
+
+
+/// The contents of an audit record which are covered by its hash
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuditBody {
+    /// The position of this record within the chain, starting from zero
+    pub seq: u64,
+
+    /// The (uncorrected) local time at which the record was made
+    pub time: Timestamp,
+
+    /// The corresponding estimate of UTC
+    pub utc: Timestamp,
+
+    /// The best-fit correction to be added to the local clock, in seconds
+    pub offset: f64,
+
+    /// The standard deviation of the offset estimate, in seconds
+    pub stddev: f64,
+
+    /// The largest permitted divergence of the local clock from UTC, in seconds
+    pub tolerance: f64,
+
+    pub within_tolerance: bool,
+
+    /// The intended interval between records, in seconds
+    pub interval: f64,
+
+    /// The servers which responded since the previous record
+    pub sources: Vec<SourceRecord>,
+
+    pub mac: MacKind,
+
+    /// The hash of the preceding record
+    pub prev: String
+}
+
+impl AuditBody {
+    /// A bound on the divergence of the local clock from UTC, at roughly 95% confidence
+    pub fn divergence(&self) -> f64 {
+        self.offset.abs() + 2.0 * self.stddev
+    }
+}
+
+
+/// A single line of the audit log
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    #[serde(flatten)]
+    pub body: AuditBody,
+
+    /// The hex-encoded hash of the serialized body
+    pub hash: String
+}
+
+
+/// The predecessor of the first record in a chain
+pub fn genesis_hash() -> String {
+    "0".repeat(64)
+}
+
+
+/// Read a secret key from a file, ignoring any trailing whitespace
+pub fn read_key(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut key = std::fs::read(path)?;
+    while key.last().is_some_and(|b| b.is_ascii_whitespace()) {
+        key.pop();
+    }
+
+    if key.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData,
+                                       "Audit key is empty"));
+    }
+
+    Ok(key)
+}
+
+
+fn digest(key: Option<&[u8]>, data: &[u8]) -> String {
+    let bytes: Vec<u8> = match key {
+        Some(key) => {
+            let mut mac = Hmac::<Sha256>::new_from_slice(key)
+                            .expect("HMAC should accept keys of any length");
+            mac.update(data);
+            mac.finalize().into_bytes().to_vec()
+        },
+        None => Sha256::digest(data).to_vec()
+    };
+
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+
+/// Serialize a record body, appending its hash as the final field
+fn encode(body: &AuditBody, key: Option<&[u8]>) -> String {
+    let json = serde_json::to_string(body).expect("Audit record should be serializable");
+    let hash = digest(key, json.as_bytes());
+
+    format!("{},\"hash\":\"{}\"}}", &json[.. json.len() - 1], hash)
+}
+
+
+/// Separate a line into the serialized body, as originally hashed, and its hash
+fn split_line(line: &str) -> Option<(String, &str)> {
+    let idx = line.rfind(",\"hash\":\"")?;
+    let hash = line[idx + 9 ..].strip_suffix("\"}")?;
+
+    Some(( format!("{}}}", &line[.. idx]), hash ))
+}
+
+
+/// The final non-blank line of a file, found by reading backwards from its end
+fn last_line<F: Read + Seek>(file: &mut F) -> std::io::Result<Option<String>> {
+    const BLOCK: u64 = 4096;
+    let mut pos = file.seek(SeekFrom::End(0))?;
+    let mut tail: Vec<u8> = vec![];
+
+    loop {
+        let len = tail.iter().rposition(|b| !b.is_ascii_whitespace()).map_or(0, |i| i + 1);
+        let start = tail[.. len].iter().rposition(|&b| b == b'\n');
+
+        if start.is_some() || pos == 0 {
+            let line = &tail[start.map_or(0, |i| i + 1) .. len];
+            return match line.is_empty() {
+                true => Ok(None),
+                false => String::from_utf8(line.to_vec()).map(Some)
+                            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            };
+        }
+
+        let from = pos.saturating_sub(BLOCK);
+        let mut block = vec![0u8; (pos - from) as usize];
+        file.seek(SeekFrom::Start(from))?;
+        file.read_exact(&mut block)?;
+        block.extend(tail);
+        ( tail, pos ) = ( block, from );
+    }
+}
+
+
+/// The sequence number and predecessor hash of the record
+/// which would follow the existing contents of an audit log
+fn chain_tail<F: Read + Seek>(file: &mut F) -> std::io::Result<(u64, String)> {
+    match last_line(file)? {
+        Some(line) => {
+            let record: AuditRecord = serde_json::from_str(&line)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            Ok(( record.body.seq + 1, record.hash ))
+        },
+        None => Ok(( 0, genesis_hash() ))
+    }
+}
+
+
+/// Prevent any other process from opening the same audit log for writing,
+/// for as long as the file remains open
+fn lock_exclusive(file: &File) -> std::io::Result<()> {
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(());
+    }
+
+    let err = std::io::Error::last_os_error();
+    match err.kind() {
+        std::io::ErrorKind::WouldBlock =>
+            Err(std::io::Error::new(std::io::ErrorKind::WouldBlock,
+                                    "Audit log is already being written by another process")),
+        _ => Err(err)
+    }
+}
+
+
+struct AuditWriter {
+    /// The log file, locked exclusively for the lifetime of the writer
+    file: File,
+    key: Option<Vec<u8>>,
+
+    /// The interval between records, and the permitted divergence from UTC, in seconds
+    interval: f64,
+    tolerance: f64,
+
+    /// The local time at which the next record should be made
+    next_due: Option<Timestamp>,
+
+    /// The latest response from each server since the previous record
+    sources: BTreeMap<String, SourceRecord>,
+
+    /// Whether a write failure has already been reported
+    failed: bool
+}
+
+
+impl AuditWriter {
+    /// Log the first of a run of failures to append records
+    fn report_failure(&mut self, err: std::io::Error) {
+        if !self.failed {
+            log::error!("Failed to write audit record - {:?}", err);
+            self.failed = true;
+        }
+    }
+}
+
+
+/// Shareable writer of periodic, hash-chained records of the clock-offset
+#[derive(Clone)]
+pub struct AuditLog {
+    writer: Arc<Mutex<AuditWriter>>
+}
+
+impl AuditLog {
+    /// The default interval between records, in seconds
+    pub const DEFAULT_INTERVAL: f32 = 60.0;
+
+    /// The default permitted divergence of the local clock from UTC, in seconds
+    pub const DEFAULT_TOLERANCE: f32 = 1.0;
+
+    /// Append to the given file, continuing the chain of any existing records,
+    /// and failing if another process is already writing to it
+    pub fn open(path: &Path, key: Option<Vec<u8>>,
+                interval: f64, tolerance: f64) -> std::io::Result<AuditLog> {
+        let mut file = std::fs::OpenOptions::new().create(true).read(true)
+                                                  .append(true).open(path)?;
+        lock_exclusive(&file)?;
+        chain_tail(&mut file)?;
+
+        Ok(AuditLog {
+            writer: Arc::new(Mutex::new(AuditWriter {
+                file, key, interval, tolerance,
+                next_due: None, sources: BTreeMap::new(), failed: false })) })
+    }
+
+    /// The audit log described by the synchronization settings, if any can be opened
+    pub fn from_config(config: &SyncConfig) -> Option<AuditLog> {
+        let path = config.audit_log_path.as_ref()?;
+        let key = match config.audit_key_path.as_deref().map(read_key).transpose() {
+            Ok(key) => key,
+            Err(e) => {
+                log::error!("Failed to read audit key {:?} - {:?}", config.audit_key_path, e);
+                return None;
+            }
+        };
+
+        AuditLog::open(path, key, config.audit_interval as f64, config.audit_tolerance as f64)
+            .map_err(|e| log::error!("Failed to open audit log {:?} - {:?}", path, e))
+            .ok()
+    }
+
+    /// Note a successful NTP exchange, for inclusion in the next record
+    pub fn observe(&self, exch: &NtpExchange) {
+        self.writer.lock().expect("Audit log is poisoned")
+            .sources.insert(exch.server.clone(), SourceRecord::new(exch));
+    }
+
+    /// The time remaining until the next record is due
+    pub fn until_due(&self, now: Timestamp) -> std::time::Duration {
+        let writer = self.writer.lock().expect("Audit log is poisoned");
+
+        writer.next_due.and_then(|t| (t - now).to_std().ok())
+                       .unwrap_or(std::time::Duration::ZERO)
+    }
+
+    /// Append a record of the given offset estimate, if one is due
+    pub fn record_if_due(&self, now: Timestamp, offs: &OffsetEvent) {
+        let mut writer = self.writer.lock().expect("Audit log is poisoned");
+        if writer.next_due.is_some_and(|t| now < t) {
+            return;
+        }
+
+        writer.next_due = Some(now + chrono::Duration::microseconds((writer.interval * 1e6) as i64));
+
+        // The chain continues from the file itself, in case it was altered while open:
+        let (seq, prev) = match chain_tail(&mut writer.file) {
+            Ok(tail) => tail,
+            Err(e) => {
+                writer.report_failure(e);
+                return;
+            }
+        };
+
+        let offset = offs.avg_offset.num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6;
+        let mut body = AuditBody {
+            seq,
+            time: now,
+            utc: now + offs.avg_offset,
+            offset,
+            stddev: offs.stddev_offset as f64,
+            tolerance: writer.tolerance,
+            within_tolerance: false,
+            interval: writer.interval,
+            sources: std::mem::take(&mut writer.sources).into_values().collect(),
+            mac: if writer.key.is_some() { MacKind::HmacSha256 } else { MacKind::Sha256 },
+            prev };
+        body.within_tolerance = body.divergence() <= body.tolerance;
+
+        let line = encode(&body, writer.key.as_deref());
+        match writeln!(writer.file, "{}", line).and_then(|_| writer.file.flush()) {
+            Ok(()) => writer.failed = false,
+            Err(e) => writer.report_failure(e)
+        }
+    }
+}
+
+
+/// Wrapper around a time-source which notes the servers contributing to each audit record
+pub struct AuditingSource<S: TimeSource> {
+    inner: S,
+    audit: AuditLog
+}
+
+impl<S: TimeSource> AuditingSource<S> {
+    pub fn new(inner: S, audit: AuditLog) -> AuditingSource<S> {
+        AuditingSource { inner, audit }
+    }
+}
+
+impl<S: TimeSource> TimeSource for AuditingSource<S> {
+    fn now(&self) -> Timestamp {
+        self.inner.now()
+    }
+
+    fn ntp_ping(&mut self, server: &str) -> Result<NtpExchange, NtpError> {
+        let result = self.inner.ntp_ping(server);
+        if let Ok(exch) = &result {
+            self.audit.observe(exch);
+        }
+
+        result
+    }
+
+    fn sleep(&mut self, dt: std::time::Duration) {
+        self.inner.sleep(dt)
+    }
+
+    fn choose_server(&mut self, count: usize) -> usize {
+        self.inner.choose_server(count)
+    }
+
+    fn take_suspension(&mut self) -> Option<std::time::Duration> {
+        self.inner.take_suspension()
+    }
+
+    fn take_network_change(&mut self) -> bool {
+        self.inner.take_network_change()
+    }
+}
+
+
+/// A defect in the integrity of an audit log
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuditIssue {
+    /// A line which could not be parsed as a record
+    Malformed { line: usize },
+
+    /// A record whose hash does not match its contents
+    BadHash { line: usize, seq: u64 },
+
+    /// A record which does not directly follow its predecessor,
+    /// e.g. because records were removed, inserted or reordered
+    BrokenChain { line: usize, seq: u64 },
+
+    /// A record whose type of hash is inconsistent with whether a key was supplied
+    MacMismatch { line: usize, seq: u64, mac: MacKind },
+
+    /// A log ending before a record known independently to have been written,
+    /// e.g. because trailing records were removed
+    Truncated { expected: u64, last: Option<u64> }
+}
+
+impl std::fmt::Display for AuditIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AuditIssue::Malformed { line } =>
+                write!(f, "line {}: malformed record", line),
+            AuditIssue::BadHash { line, seq } =>
+                write!(f, "line {}: hash does not match contents of record {}", line, seq),
+            AuditIssue::BrokenChain { line, seq } =>
+                write!(f, "line {}: record {} does not follow its predecessor", line, seq),
+            AuditIssue::MacMismatch { line, seq, mac: MacKind::HmacSha256 } =>
+                write!(f, "line {}: record {} requires a key for verification", line, seq),
+            AuditIssue::MacMismatch { line, seq, mac: MacKind::Sha256 } =>
+                write!(f, "line {}: record {} is not keyed", line, seq),
+            AuditIssue::Truncated { expected, last: Some(last) } =>
+                write!(f, "log ends at record {}, before expected record {}", last, expected),
+            AuditIssue::Truncated { expected, last: None } =>
+                write!(f, "log has no records, but record {} was expected", expected)
+        }
+    }
+}
+
+
+/// An interval between successive records which is much longer than intended
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gap {
+    pub from: Timestamp,
+    pub to: Timestamp
+}
+
+
+/// A period during which the local clock may have diverged from UTC by more than the tolerance
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exceedance {
+    /// The time of the first record outside the tolerance
+    pub start: Timestamp,
+
+    /// The time of the next record within tolerance, or of the last record outside it
+    pub end: Timestamp,
+
+    /// The number of records outside the tolerance
+    pub records: u32,
+
+    /// The largest bound on the divergence from UTC, in seconds
+    pub max_divergence: f64
+}
+
+
+/// The outcome of checking an audit log
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AuditVerification {
+    /// The number of well-formed records
+    pub records: u32,
+
+    pub first: Option<Timestamp>,
+    pub last: Option<Timestamp>,
+
+    /// The sequence number and hash of the final well-formed record,
+    /// which can be noted elsewhere to detect later removal of trailing records
+    pub last_seq: Option<u64>,
+    pub last_hash: Option<String>,
+
+    pub issues: Vec<AuditIssue>,
+    pub gaps: Vec<Gap>,
+    pub exceedances: Vec<Exceedance>
+}
+
+impl AuditVerification {
+    /// Whether the log is complete and unaltered
+    pub fn intact(&self) -> bool {
+        self.records > 0 && self.issues.is_empty() && self.gaps.is_empty()
+    }
+
+    /// Require that the log extends at least to the given record,
+    /// since removing trailing records otherwise leaves an intact chain
+    pub fn expect_seq(&mut self, expected: u64) {
+        if self.last_seq.is_none_or(|last| last < expected) {
+            self.issues.push(AuditIssue::Truncated { expected, last: self.last_seq });
+        }
+    }
+
+    /// Render as a human-readable summary
+    pub fn to_text(&self) -> String {
+        let fmt_time = |t: &Timestamp| t.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+        let mut txt = format!("records: {}", self.records);
+
+        if let ( Some(first), Some(last) ) = ( &self.first, &self.last ) {
+            txt += &format!(" from {} to {}", fmt_time(first), fmt_time(last));
+        }
+        if let ( Some(seq), Some(hash) ) = ( self.last_seq, &self.last_hash ) {
+            txt += &format!("\nfinal record: {} {}", seq, hash);
+        }
+        txt += &format!("\nintegrity: {}\n", if self.intact() { "intact" }
+                                             else if self.records == 0 { "no records" }
+                                             else { "FAILED" });
+        for issue in self.issues.iter() {
+            txt += &format!("  {}\n", issue);
+        }
+
+        txt += if self.gaps.is_empty() { "gaps: none\n" } else { "gaps:\n" };
+        for gap in self.gaps.iter() {
+            txt += &format!("  {} to {} ({:.1}s)\n", fmt_time(&gap.from), fmt_time(&gap.to),
+                            (gap.to - gap.from).num_milliseconds() as f64 * 1e-3);
+        }
+
+        txt += if self.exceedances.is_empty() { "tolerance exceeded: never\n" }
+               else { "tolerance exceeded:\n" };
+        for exc in self.exceedances.iter() {
+            txt += &format!("  {} to {} ({} records, divergence up to {:.3}ms)\n",
+                            fmt_time(&exc.start), fmt_time(&exc.end),
+                            exc.records, exc.max_divergence * 1e3);
+        }
+
+        txt
+    }
+}
+
+
+/// Check the hashes and continuity of an audit log, and find periods outside the tolerance,
+/// which defaults to that recorded with each record
+pub fn verify<R: BufRead>(rdr: R, key: Option<&[u8]>,
+                          tolerance: Option<f64>) -> std::io::Result<AuditVerification> {
+    let mut result = AuditVerification::default();
+    let mut prev: Option<AuditRecord> = None;
+    let mut exceedance: Option<Exceedance> = None;
+
+    for (idx, line) in rdr.lines().enumerate() {
+        let line = line?;
+        let lineno = idx + 1;
+        if line.trim().is_empty() { continue; }
+
+        let parsed = serde_json::from_str::<AuditRecord>(&line).ok()
+                        .zip(split_line(&line));
+        let (record, (body_json, hash)) = match parsed {
+            Some(p) => p,
+            None => {
+                result.issues.push(AuditIssue::Malformed { line: lineno });
+                continue;
+            }
+        };
+        let seq = record.body.seq;
+
+        let expected_mac = if key.is_some() { MacKind::HmacSha256 } else { MacKind::Sha256 };
+        if record.body.mac != expected_mac {
+            result.issues.push(AuditIssue::MacMismatch { line: lineno, seq,
+                                                         mac: record.body.mac });
+        } else if digest(key, body_json.as_bytes()) != hash {
+            result.issues.push(AuditIssue::BadHash { line: lineno, seq });
+        }
+
+        let follows = match &prev {
+            Some(p) => record.body.prev == p.hash && seq == p.body.seq + 1,
+            None => record.body.prev == genesis_hash() && seq == 0
+        };
+        if !follows {
+            result.issues.push(AuditIssue::BrokenChain { line: lineno, seq });
+        }
+
+        if let Some(p) = &prev {
+            let limit = chrono::Duration::microseconds((2e6 * p.body.interval) as i64);
+            if record.body.time - p.body.time > limit {
+                result.gaps.push(Gap { from: p.body.time, to: record.body.time });
+            }
+        }
+
+        let divergence = record.body.divergence();
+        if divergence > tolerance.unwrap_or(record.body.tolerance) {
+            let exc = exceedance.get_or_insert(Exceedance {
+                start: record.body.time, end: record.body.time,
+                records: 0, max_divergence: 0.0 });
+            exc.end = record.body.time;
+            exc.records += 1;
+            exc.max_divergence = exc.max_divergence.max(divergence);
+        } else if let Some(mut exc) = exceedance.take() {
+            exc.end = record.body.time;
+            result.exceedances.push(exc);
+        }
+
+        result.records += 1;
+        result.first.get_or_insert(record.body.time);
+        result.last = Some(record.body.time);
+        result.last_seq = Some(seq);
+        result.last_hash = Some(record.hash.clone());
+        prev = Some(record);
+    }
+    result.exceedances.extend(exceedance);
+
+    Ok(result)
+}
+
+
+/// Check an audit log file, as for verify()
+pub fn verify_path(path: &Path, key: Option<&[u8]>,
+                   tolerance: Option<f64>) -> std::io::Result<AuditVerification> {
+    verify(std::io::BufReader::new(File::open(path)?), key, tolerance)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sim::{ Scenario, SimNetwork },
+        testing::* };
+
+    fn offset(ms: i64, stddev: f32) -> OffsetEvent {
+        OffsetEvent { avg_offset: chrono::Duration::milliseconds(ms), stddev_offset: stddev }
+    }
+
+    /// Write records once per minute, with the offset exceeding a 10ms tolerance
+    /// at 3-4 minutes, and a gap at 6-9 minutes
+    fn write_log(path: &Path, key: Option<&[u8]>) -> String {
+        let _ = std::fs::remove_file(path);
+        let audit = AuditLog::open(path, key.map(Vec::from), 60.0, 0.01).unwrap();
+
+        let scen = Scenario::default();
+        let mut src = AuditingSource::new(SimNetwork::new(&scen), audit.clone());
+        let _ = src.ntp_ping("good.example");
+
+        for minute in [ 0, 1, 2, 3, 4, 5, 6, 9, 10 ] {
+            let ms = if minute == 3 || minute == 4 { 12 } else { -2 };
+            audit.record_if_due(mk_time(60 * minute, (0, 0, 0)), &offset(ms, 0.001));
+            // Records are not repeated before the interval has elapsed:
+            audit.record_if_due(mk_time(60 * minute + 30, (0, 0, 0)), &offset(ms, 0.001));
+        }
+
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    fn check(doc: &str, key: Option<&[u8]>) -> AuditVerification {
+        verify(std::io::Cursor::new(doc), key, None).unwrap()
+    }
+
+    #[test]
+    fn intact_chain() {
+        let path = temp_path("audit-intact.jsonl");
+        let doc = write_log(&path, None);
+        let result = check(&doc, None);
+
+        assert_eq!(result.records, 9);
+        assert!(result.issues.is_empty());
+        assert_eq!(result.gaps, [ Gap { from: mk_time(360, (0, 0, 0)),
+                                        to: mk_time(540, (0, 0, 0)) } ]);
+        assert!(!result.intact());
+        assert_eq!(result.exceedances.len(), 1);
+        let exc = &result.exceedances[0];
+        assert_eq!(( exc.start, exc.end, exc.records ),
+                   ( mk_time(180, (0, 0, 0)), mk_time(300, (0, 0, 0)), 2 ));
+        assert_close(exc.max_divergence, 0.014, 1e-9);
+        let last: AuditRecord = serde_json::from_str(doc.lines().last().unwrap()).unwrap();
+        assert!(result.to_text().contains(&format!("final record: 8 {}\n", last.hash)));
+
+        let first: AuditRecord = serde_json::from_str(doc.lines().next().unwrap()).unwrap();
+        assert_eq!(first.body.prev, genesis_hash());
+        assert_eq!(first.body.sources.len(), 1);
+        assert_eq!(first.body.sources[0].ref_id, "83.73.77.0");
+        assert_eq!(first.body.utc, mk_time(0, (0, 0, 0)) - chrono::Duration::milliseconds(2));
+
+        // A stricter tolerance flags every record:
+        let strict = verify(std::io::Cursor::new(&doc), None, Some(0.001)).unwrap();
+        assert_eq!(strict.exceedances.len(), 1);
+        assert_eq!(strict.exceedances[0].records, 9);
+
+        // Reopening the log continues the chain:
+        let audit = AuditLog::open(&path, None, 60.0, 0.01).unwrap();
+        audit.record_if_due(mk_time(660, (0, 0, 0)), &offset(-2, 0.001));
+        let result = verify_path(&path, None, None).unwrap();
+        assert_eq!(( result.records, result.issues.len() ), ( 10, 0 ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn tampering() {
+        let path = temp_path("audit-tamper.jsonl");
+        let doc = write_log(&path, None);
+        let lines: Vec<&str> = doc.lines().collect();
+
+        let edited = doc.replacen(r#""offset":0.012"#, r#""offset":0.002"#, 1);
+        assert_eq!(check(&edited, None).issues, [ AuditIssue::BadHash { line: 4, seq: 3 } ]);
+
+        let removed = [ &lines[.. 2], &lines[3 ..] ].concat().join("\n");
+        assert_eq!(check(&removed, None).issues,
+                   [ AuditIssue::BrokenChain { line: 3, seq: 3 } ]);
+
+        // Removing trailing records leaves an intact chain, unless its length is known:
+        let shortened = lines[.. 7].join("\n");
+        let mut result = check(&shortened, None);
+        assert!(result.issues.is_empty());
+        assert_eq!(result.last_seq, Some(6));
+        result.expect_seq(6);
+        assert!(result.issues.is_empty());
+        result.expect_seq(8);
+        assert_eq!(result.issues, [ AuditIssue::Truncated { expected: 8, last: Some(6) } ]);
+        assert!(result.to_text().contains("log ends at record 6, before expected record 8"));
+
+        let truncated = lines[1 ..].join("\n");
+        assert_eq!(check(&truncated, None).issues,
+                   [ AuditIssue::BrokenChain { line: 1, seq: 1 } ]);
+
+        let garbled = doc.replacen("\"hash\"", "\"hush\"", 1);
+        assert_eq!(check(&garbled, None).issues[0], AuditIssue::Malformed { line: 1 });
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn single_writer() {
+        let path = temp_path("audit-writer.jsonl");
+        let doc = write_log(&path, None);
+        let audit = AuditLog::open(&path, None, 60.0, 0.01).unwrap();
+
+        let err = AuditLog::open(&path, None, 60.0, 0.01).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+
+        // Appends follow the file's contents, even if altered while open:
+        let kept: String = doc.lines().take(2).map(|l| format!("{}\n", l)).collect();
+        std::fs::write(&path, kept).unwrap();
+        audit.record_if_due(mk_time(660, (0, 0, 0)), &offset(-2, 0.001));
+        let result = verify_path(&path, None, None).unwrap();
+        assert_eq!(( result.records, result.issues.len() ), ( 3, 0 ));
+
+        drop(audit);
+        assert!(AuditLog::open(&path, None, 60.0, 0.01).is_ok());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn tail_lines() {
+        let tail = |txt: &str| last_line(&mut std::io::Cursor::new(txt)).unwrap();
+        let long = "x".repeat(10_000);
+
+        assert_eq!(tail(""), None);
+        assert_eq!(tail(" \n\n"), None);
+        assert_eq!(tail("first\nsecond\n\n").as_deref(), Some("second"));
+        assert_eq!(tail(&long).as_deref(), Some(long.as_str()));
+        assert_eq!(tail(&format!("{}\nlast{}\n", long, long)),
+                   Some(format!("last{}", long)));
+        assert_eq!(tail(&format!("first\n{}", " ".repeat(10_000))).as_deref(), Some("first"));
+    }
+
+    #[test]
+    fn keyed_chain() {
+        let path = temp_path("audit-keyed.jsonl");
+        let doc = write_log(&path, Some(b"secret"));
+
+        assert!(check(&doc, Some(b"secret")).issues.is_empty());
+        assert_eq!(check(&doc, Some(b"guess")).issues.len(), 9);
+        assert_eq!(check(&doc, None).issues[0],
+                   AuditIssue::MacMismatch { line: 1, seq: 0, mac: MacKind::HmacSha256 });
+
+        // Without the key, a forger can only produce unkeyed hashes:
+        let unkeyed = write_log(&path, None);
+        assert_eq!(check(&unkeyed, Some(b"secret")).issues[0],
+                   AuditIssue::MacMismatch { line: 1, seq: 0, mac: MacKind::Sha256 });
+        assert!(check(&unkeyed, Some(b"secret")).to_text().contains("integrity: FAILED"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+// (C)Copyright 2026, RW Penney
@@ -0,0 +1,293 @@
+/*
+ *  Publish/subscribe distribution of clock events for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use std::sync::{ Arc, Mutex, mpsc };
+use crate::{
+    PollEvent, TickEvent, Timestamp, UImessage,
+    ntp::{ NtpError, NtpExchange },
+    sync::TimeSource };
+
+
+/// Selection of clock-ticks to be delivered to a subscriber
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TickFilter {
+    /// No clock-ticks
+    Nothing,
+
+    /// Every clock-tick, at the Ticker's full rate
+    All,
+
+    /// Only ticks on whole-second boundaries
+    WholeSeconds,
+
+    /// Only ticks on whole-minute boundaries
+    WholeMinutes,
+
+    /// Only ticks on multiples of the given interval (in microseconds) since the epoch
+    Every(i64)
+}
+
+impl TickFilter {
+    pub fn accepts(self, tick: &TickEvent) -> bool {
+        let every = |period_us: i64| period_us > 0
+                        && tick.t_nominal.timestamp_micros() % period_us == 0;
+
+        match self {
+            TickFilter::Nothing =>          false,
+            TickFilter::All =>              true,
+            TickFilter::WholeSeconds =>     every(1_000_000),
+            TickFilter::WholeMinutes =>     every(60_000_000),
+            TickFilter::Every(period_us) => every(period_us)
+        }
+    }
+}
+
+
+/// Selection of the events to be delivered to a subscriber
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventFilter {
+    pub ticks: TickFilter,
+
+    /// Whether to deliver clock-offset estimates
+    pub offsets: bool,
+
+    /// Whether to deliver calibration, scheduling and polling status reports
+    pub status: bool
+}
+
+impl EventFilter {
+    /// Accept every event
+    pub fn all() -> EventFilter {
+        EventFilter { ticks: TickFilter::All, offsets: true, status: true }
+    }
+
+    /// Accept only clock-offset estimates
+    pub fn offsets() -> EventFilter {
+        EventFilter { ticks: TickFilter::Nothing, offsets: true, status: false }
+    }
+
+    /// Accept only the selected clock-ticks
+    pub fn ticks(ticks: TickFilter) -> EventFilter {
+        EventFilter { ticks, offsets: false, status: false }
+    }
+
+    pub fn accepts(&self, event: &UImessage) -> bool {
+        match event {
+            UImessage::Tick(tick) =>        self.ticks.accepts(tick),
+            UImessage::Offset(_) =>         self.offsets,
+            UImessage::Calibration(_) |
+            UImessage::Realtime(_) |
+            UImessage::Poll(_) =>           self.status
+        }
+    }
+}
+
+
+/// Recipient of published events
+pub trait EventSink: Send {
+    /// Accept an event, returning false if the subscriber has gone away
+    fn deliver(&mut self, event: &UImessage) -> bool;
+}
+
+#[cfg(feature = "gtk")]
+impl EventSink for crate::UIsender {
+    fn deliver(&mut self, event: &UImessage) -> bool {
+        self.send(event.clone()).is_ok()
+    }
+}
+
+impl EventSink for mpsc::Sender<UImessage> {
+    fn deliver(&mut self, event: &UImessage) -> bool {
+        self.send(event.clone()).is_ok()
+    }
+}
+
+impl<F: FnMut(&UImessage) -> bool + Send> EventSink for F {
+    fn deliver(&mut self, event: &UImessage) -> bool {
+        self(event)
+    }
+}
+
+
+struct Subscription {
+    filter: EventFilter,
+    sink: Box<dyn EventSink>
+}
+
+
+/// Shared hub through which clock-ticks, offset estimates and status reports
+/// are distributed to any number of subscribers
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Subscription>>>
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus::default()
+    }
+
+    /// Register a recipient for all events accepted by a filter
+    pub fn subscribe<S: EventSink + 'static>(&self, filter: EventFilter, sink: S) {
+        self.subscribers.lock().unwrap()
+            .push(Subscription { filter, sink: Box::new(sink) });
+    }
+
+    /// Register a new channel for all events accepted by a filter
+    pub fn subscribe_channel(&self, filter: EventFilter) -> mpsc::Receiver<UImessage> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribe(filter, sender);
+
+        receiver
+    }
+
+    /// The number of currently registered subscribers
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+
+    /// Deliver an event to all interested subscribers,
+    /// discarding any whose receiving end has been closed
+    pub fn publish(&self, event: UImessage) {
+        self.subscribers.lock().unwrap()
+            .retain_mut(|sub| !sub.filter.accepts(&event) || sub.sink.deliver(&event));
+    }
+}
+
+
+/// Wrapper around a time-source which publishes the outcome of every NTP request
+pub struct PublishingSource<S: TimeSource> {
+    inner: S,
+    bus: EventBus
+}
+
+impl<S: TimeSource> PublishingSource<S> {
+    pub fn new(inner: S, bus: EventBus) -> PublishingSource<S> {
+        PublishingSource { inner, bus }
+    }
+}
+
+impl<S: TimeSource> TimeSource for PublishingSource<S> {
+    fn now(&self) -> Timestamp {
+        self.inner.now()
+    }
+
+    fn ntp_ping(&mut self, server: &str) -> Result<NtpExchange, NtpError> {
+        let result = self.inner.ntp_ping(server);
+        self.bus.publish(UImessage::Poll(PollEvent { server: String::from(server),
+                                                     time: self.inner.now(),
+                                                     result: result.clone() }));
+
+        result
+    }
+
+    fn sleep(&mut self, dt: std::time::Duration) {
+        self.inner.sleep(dt)
+    }
+
+    fn choose_server(&mut self, count: usize) -> usize {
+        self.inner.choose_server(count)
+    }
+
+    fn take_suspension(&mut self) -> Option<std::time::Duration> {
+        self.inner.take_suspension()
+    }
+
+    fn take_network_change(&mut self) -> bool {
+        self.inner.take_network_change()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        OffsetEvent,
+        sim::{ Scenario, SimNetwork },
+        testing::* };
+
+    fn tick(secs: i32, ms: i32) -> UImessage {
+        UImessage::Tick(mk_tick(secs, ms))
+    }
+
+    fn offset() -> UImessage {
+        UImessage::Offset(OffsetEvent { avg_offset: chrono::Duration::zero(),
+                                        stddev_offset: 0.1 })
+    }
+
+    #[test]
+    fn tick_filters() {
+        assert!(TickFilter::All.accepts(&mk_tick(7, 250)));
+        assert!(!TickFilter::Nothing.accepts(&mk_tick(7, 0)));
+        assert!(TickFilter::WholeSeconds.accepts(&mk_tick(7, 0)));
+        assert!(!TickFilter::WholeSeconds.accepts(&mk_tick(7, 500)));
+        assert!(TickFilter::WholeMinutes.accepts(&mk_tick(120, 0)));
+        assert!(!TickFilter::WholeMinutes.accepts(&mk_tick(121, 0)));
+        assert!(TickFilter::Every(500_000).accepts(&mk_tick(3, 500)));
+        assert!(!TickFilter::Every(0).accepts(&mk_tick(3, 0)));
+    }
+
+    #[test]
+    fn fanout() {
+        let bus = EventBus::new();
+        let everything = bus.subscribe_channel(EventFilter::all());
+        let seconds = bus.subscribe_channel(EventFilter::ticks(TickFilter::WholeSeconds));
+        let offsets = bus.subscribe_channel(EventFilter::offsets());
+
+        for ms in [ 0, 250, 500, 750 ] {
+            bus.publish(tick(60, ms));
+        }
+        bus.publish(offset());
+        bus.publish(tick(61, 0));
+
+        assert_eq!(everything.try_iter().count(), 6);
+        assert_eq!(seconds.try_iter().count(), 2);
+        assert!(matches!(offsets.try_iter().collect::<Vec<_>>().as_slice(),
+                         [ UImessage::Offset(_) ]));
+    }
+
+    #[test]
+    fn unsubscription() {
+        let bus = EventBus::new();
+        let kept = bus.subscribe_channel(EventFilter::all());
+        drop(bus.subscribe_channel(EventFilter::all()));
+
+        let mut count = 0;
+        bus.subscribe(EventFilter::offsets(), move |_: &UImessage| {
+            count += 1;
+            count < 2
+        });
+        assert_eq!(bus.subscriber_count(), 3);
+
+        bus.publish(tick(0, 0));
+        assert_eq!(bus.subscriber_count(), 2);
+        bus.publish(offset());
+        bus.publish(offset());
+        assert_eq!(bus.subscriber_count(), 1);
+        assert_eq!(kept.try_iter().count(), 3);
+    }
+
+    #[test]
+    fn published_polls() {
+        let bus = EventBus::new();
+        let status = bus.subscribe_channel(EventFilter::all());
+        let offsets = bus.subscribe_channel(EventFilter::offsets());
+        let mut src = PublishingSource::new(SimNetwork::new(&Scenario::default()), bus);
+
+        let result = src.ntp_ping("good.example");
+        match status.try_iter().collect::<Vec<_>>().as_slice() {
+            [ UImessage::Poll(poll) ] => {
+                assert_eq!(poll.server, "good.example");
+                assert_eq!(poll.result, result);
+                assert_eq!(poll.time, src.now());
+            },
+            other => panic!("Unexpected events {:?}", other)
+        }
+        assert_eq!(offsets.try_iter().count(), 0);
+    }
+}
+
+// (C)Copyright 2026, RW Penney
@@ -5,7 +5,10 @@
 
 use dirs;
 use serde::Deserialize;
-use std::path::Path;
+use crate::{
+    display::{ DigitSuppression, DisplayFormat },
+    rt::{ RealtimeRequest, SchedPolicy } };
+use std::path::{ Path, PathBuf };
 use toml;
 
 
@@ -18,7 +21,10 @@ pub enum ConfigReadError {
     TomlError(toml::de::Error),
 
     /// A failure to identify OS-specific config-directory location
-    UnknownHome
+    UnknownHome,
+
+    /// A setting whose value is outside its permitted range
+    InvalidValue(String)
 }
 
 impl From<std::io::Error> for ConfigReadError {
@@ -47,7 +53,43 @@ pub struct SyncConfig {
 
     /// The minimum time interval between NTP requests, in seconds
     #[serde(default = "SyncConfig::default_wakeup_itvl")]
-    pub wakeup_interval: f32
+    pub wakeup_interval: f32,
+
+    /// An optional file to which every NTP exchange is appended as JSON-Lines
+    #[serde(default)]
+    pub record_path: Option<PathBuf>,
+
+    /// Whether to re-poll NTP servers promptly when network addresses or routes change
+    #[serde(default = "SyncConfig::default_watch_network")]
+    pub watch_network: bool,
+
+    /// An optional file to which polls, offset estimates and resets are appended as JSON-Lines
+    #[serde(default)]
+    pub event_log_path: Option<PathBuf>,
+
+    /// The size, in bytes, above which the event log is rotated, or zero for no limit
+    #[serde(default = "SyncConfig::default_event_log_max_bytes")]
+    pub event_log_max_bytes: u64,
+
+    /// The number of rotated event logs retained
+    #[serde(default = "SyncConfig::default_event_log_keep")]
+    pub event_log_keep: u32,
+
+    /// An optional file to which hash-chained records of the clock-offset are appended
+    #[serde(default)]
+    pub audit_log_path: Option<PathBuf>,
+
+    /// The interval between audit records, in seconds
+    #[serde(default = "SyncConfig::default_audit_interval")]
+    pub audit_interval: f32,
+
+    /// The largest permitted divergence of the local clock from UTC, in seconds
+    #[serde(default = "SyncConfig::default_audit_tolerance")]
+    pub audit_tolerance: f32,
+
+    /// An optional file containing a secret key with which audit records are signed
+    #[serde(default)]
+    pub audit_key_path: Option<PathBuf>
 }
 
 impl SyncConfig {
@@ -59,32 +101,323 @@ impl SyncConfig {
         crate::sync::OffsetEstimator::DEFAULT_WAKEUP_ITVL
     }
 
-    pub fn default() -> SyncConfig {
+    fn default_watch_network() -> bool {
+        true
+    }
+
+    fn default_event_log_max_bytes() -> u64 {
+        crate::eventlog::EventLog::DEFAULT_MAX_BYTES
+    }
+
+    fn default_event_log_keep() -> u32 {
+        crate::eventlog::EventLog::DEFAULT_KEEP
+    }
+
+    fn default_audit_interval() -> f32 {
+        crate::audit::AuditLog::DEFAULT_INTERVAL
+    }
+
+    fn default_audit_tolerance() -> f32 {
+        crate::audit::AuditLog::DEFAULT_TOLERANCE
+    }
+
+    fn validate(&self) -> Result<(), ConfigReadError> {
+        if self.ntp_servers.is_empty() {
+            return Err(ConfigReadError::InvalidValue(
+                String::from("ntp_servers must contain at least one hostname")));
+        }
+
+        let intervals = [ ( "target_precision", self.target_precision ),
+                          ( "wakeup_interval", self.wakeup_interval ),
+                          ( "audit_interval", self.audit_interval ) ];
+        for (name, value) in intervals {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(ConfigReadError::InvalidValue(
+                    format!("{}={} must be a positive number of seconds",
+                            name, value)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for SyncConfig {
+    fn default() -> SyncConfig {
         SyncConfig {
             ntp_servers:
                 DEFAULT_NTP_SERVERS.into_iter()
-                                   .map(|h| String::from(h)).collect(),
+                                   .map(String::from).collect(),
             target_precision: SyncConfig::default_tgt_precision(),
-            wakeup_interval: SyncConfig::default_wakeup_itvl()
+            wakeup_interval: SyncConfig::default_wakeup_itvl(),
+            record_path: None,
+            watch_network: SyncConfig::default_watch_network(),
+            event_log_path: None,
+            event_log_max_bytes: SyncConfig::default_event_log_max_bytes(),
+            event_log_keep: SyncConfig::default_event_log_keep(),
+            audit_log_path: None,
+            audit_interval: SyncConfig::default_audit_interval(),
+            audit_tolerance: SyncConfig::default_audit_tolerance(),
+            audit_key_path: None
+        }
+    }
+}
+
+
+/// Hyperparameters of the statistical model of the clock-offset
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct EstimatorConfig {
+    /// The initial uncertainty in the clock-offset, in seconds
+    #[serde(default = "EstimatorConfig::default_prior_stddev")]
+    pub prior_stddev: f32,
+
+    /// The diffusive growth rate of the offset uncertainty,
+    /// in seconds per square-root day
+    #[serde(default = "EstimatorConfig::default_diffusivity")]
+    pub diffusivity: f32,
+
+    /// The assumed ratio between the error in a measured offset
+    /// and the round-trip time of its NTP exchange
+    #[serde(default = "EstimatorConfig::default_rtt_factor")]
+    pub rtt_error_factor: f32,
+
+    /// Whether to learn the relationship between measurement error
+    /// and round-trip time for each server, in place of rtt_error_factor
+    #[serde(default = "EstimatorConfig::default_calibrate_rtt")]
+    pub calibrate_rtt: bool,
+
+    /// The additional uncertainty in the clock-offset, in seconds,
+    /// assumed after the system resumes from suspension
+    #[serde(default = "EstimatorConfig::default_resume_stddev")]
+    pub resume_stddev: f32
+}
+
+impl EstimatorConfig {
+    fn default_prior_stddev() -> f32 {
+        crate::sync::OffsetTracker::DEFAULT_PRIOR_STDDEV
+    }
+
+    fn default_diffusivity() -> f32 {
+        crate::stats::BayesOffset::DEFAULT_DIFFUSIVITY
+    }
+
+    fn default_rtt_factor() -> f32 {
+        crate::sync::OffsetTracker::DEFAULT_RTT_FACTOR
+    }
+
+    fn default_calibrate_rtt() -> bool {
+        true
+    }
+
+    fn default_resume_stddev() -> f32 {
+        crate::sync::OffsetTracker::DEFAULT_RESUME_STDDEV
+    }
+
+    /// Render these settings as a section of a TOML configuration file
+    pub fn to_toml(&self) -> String {
+        format!("[estimator]\nprior_stddev = {:?}\ndiffusivity = {:?}\nrtt_error_factor = {:?}\ncalibrate_rtt = {}\n",
+                self.prior_stddev, self.diffusivity, self.rtt_error_factor,
+                self.calibrate_rtt)
+    }
+}
+
+impl Default for EstimatorConfig {
+    fn default() -> EstimatorConfig {
+        EstimatorConfig {
+            prior_stddev: EstimatorConfig::default_prior_stddev(),
+            diffusivity: EstimatorConfig::default_diffusivity(),
+            rtt_error_factor: EstimatorConfig::default_rtt_factor(),
+            calibrate_rtt: EstimatorConfig::default_calibrate_rtt(),
+            resume_stddev: EstimatorConfig::default_resume_stddev()
         }
     }
 }
 
 
+/// Settings for the visual presentation of the clock
 #[derive(Clone, Debug, Deserialize)]
-pub struct ECConfig {
-    pub sync: SyncConfig
+pub struct DisplayConfig {
+    /// The time interval between screen updates, in milliseconds,
+    /// which must divide exactly into one second
+    #[serde(default = "DisplayConfig::default_tick_period")]
+    pub tick_period_ms: u32,
+
+    /// The resolution with which the time of day is shown
+    #[serde(default)]
+    pub format: DisplayFormat,
+
+    /// The treatment of sub-second digits which are finer than the clock uncertainty
+    #[serde(default)]
+    pub insignificant_digits: DigitSuppression
 }
 
-impl ECConfig {
-    const CFG_FILENAME: &str = "eng-clock.toml";
+impl DisplayConfig {
+    /// The shortest permitted interval between screen updates, in milliseconds
+    const MIN_TICK_PERIOD: u32 = 10;
+
+    fn default_tick_period() -> u32 {
+        (crate::ticker::Ticker::DEFAULT_PERIOD_US / 1000) as u32
+    }
+
+    fn validate(&self) -> Result<(), ConfigReadError> {
+        let period = self.tick_period_ms;
+
+        if period < DisplayConfig::MIN_TICK_PERIOD || 1000 % period != 0 {
+            return Err(ConfigReadError::InvalidValue(
+                format!("tick_period_ms={} must be a divisor of 1000 and at least {}",
+                        period, DisplayConfig::MIN_TICK_PERIOD)));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for DisplayConfig {
+    fn default() -> DisplayConfig {
+        DisplayConfig {
+            tick_period_ms: DisplayConfig::default_tick_period(),
+            format: DisplayFormat::default(),
+            insignificant_digits: DigitSuppression::default()
+        }
+    }
+}
+
+
+/// The mechanism by which the Ticker sleeps until the next tick
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WaitStrategy {
+    /// Sleep for a computed interval
+    #[default]
+    Relative,
+
+    /// Sleep until an absolute time (via clock_nanosleep on Linux)
+    Absolute
+}
+
+
+/// Settings for the clock-ticking thread
+#[derive(Clone, Debug, Deserialize)]
+pub struct TickerConfig {
+    /// The mechanism used to sleep until the next tick
+    #[serde(default)]
+    pub wait: WaitStrategy,
+
+    /// The interval before each tick, in microseconds, spent busy-waiting
+    /// rather than sleeping, when using absolute waits
+    #[serde(default)]
+    pub spin_us: u32,
+
+    /// The largest rate at which changes in the clock-offset are phased in,
+    /// as a fraction of elapsed time, or zero to apply changes immediately
+    #[serde(default)]
+    pub slew_rate: f32,
+
+    /// The change in clock-offset, in milliseconds, above which
+    /// the change is applied immediately rather than slewed
+    #[serde(default = "TickerConfig::default_step_threshold")]
+    pub step_threshold_ms: u32,
+
+    /// The operating-system scheduling policy for the ticker thread
+    #[serde(default)]
+    pub sched_policy: SchedPolicy,
+
+    /// The static priority used with a real-time scheduling policy
+    #[serde(default = "TickerConfig::default_sched_priority")]
+    pub sched_priority: i32,
+
+    /// The CPUs on which the ticker thread may run, or empty for no restriction
+    #[serde(default)]
+    pub cpu_affinity: Vec<usize>,
+
+    /// Whether all process memory should be locked into RAM
+    #[serde(default)]
+    pub lock_memory: bool
+}
+
+impl TickerConfig {
+    /// The largest slew rate which avoids skipping or repeating ticks
+    pub const MAX_SLEW_RATE: f32 = 0.2;
+
+    fn default_step_threshold() -> u32 {
+        1000
+    }
+
+    fn default_sched_priority() -> i32 {
+        10
+    }
+
+    /// The real-time settings to be applied to the ticker thread
+    pub fn realtime_request(&self) -> RealtimeRequest {
+        RealtimeRequest {
+            policy: self.sched_policy,
+            priority: self.sched_priority,
+            cpu_affinity: self.cpu_affinity.clone(),
+            lock_memory: self.lock_memory
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigReadError> {
+        if !(0.0 ..= TickerConfig::MAX_SLEW_RATE).contains(&self.slew_rate) {
+            return Err(ConfigReadError::InvalidValue(
+                format!("slew_rate={} must lie between 0 and {}",
+                        self.slew_rate, TickerConfig::MAX_SLEW_RATE)));
+        }
+        if self.sched_policy != SchedPolicy::Other
+                && !(1 ..= 99).contains(&self.sched_priority) {
+            return Err(ConfigReadError::InvalidValue(
+                format!("sched_priority={} must lie between 1 and 99",
+                        self.sched_priority)));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for TickerConfig {
+    fn default() -> TickerConfig {
+        TickerConfig {
+            wait: WaitStrategy::default(),
+            spin_us: 0,
+            slew_rate: 0.0,
+            step_threshold_ms: TickerConfig::default_step_threshold(),
+            sched_policy: SchedPolicy::default(),
+            sched_priority: TickerConfig::default_sched_priority(),
+            cpu_affinity: vec![],
+            lock_memory: false
+        }
+    }
+}
+
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ECConfig {
+    pub sync: SyncConfig,
+
+    #[serde(default)]
+    pub estimator: EstimatorConfig,
+
+    #[serde(default)]
+    pub display: DisplayConfig,
+
+    #[serde(default)]
+    pub ticker: TickerConfig
+}
 
+impl Default for ECConfig {
     /// Create a configuration parameters from a built-in global list of NTP servers
-    pub fn default() -> ECConfig {
+    fn default() -> ECConfig {
         ECConfig {
-            sync: SyncConfig::default()
+            sync: SyncConfig::default(),
+            estimator: EstimatorConfig::default(),
+            display: DisplayConfig::default(),
+            ticker: TickerConfig::default()
         }
     }
+}
+
+impl ECConfig {
+    const CFG_FILENAME: &str = "eng-clock.toml";
 
     /// Read configuration settings from a TOML file
     ///
@@ -98,10 +431,189 @@ impl ECConfig {
     ///         "1.europe.pool.ntp.org", "1.north-america.pool.ntp.org",
     ///         "1.oceania.pool.ntp.org", "1.south-america.pool.ntp.org" ]"#).unwrap();
     /// assert_eq!(cfg.sync.ntp_servers.len(), 6);
+    ///
+    /// let cfg = ECConfig::from_toml(r#"
+    ///         [sync]
+    ///         ntp_servers = [ "ntp.example" ]
+    ///         [estimator]
+    ///         diffusivity = 0.2"#).unwrap();
+    /// assert_eq!(cfg.estimator.diffusivity, 0.2);
+    /// assert_eq!(cfg.estimator.rtt_error_factor, 0.25);
+    ///
+    /// let cfg = ECConfig::from_toml(r#"
+    ///         [sync]
+    ///         ntp_servers = [ "ntp.example" ]
+    ///         [display]
+    ///         tick_period_ms = 100
+    ///         format = "tenths""#).unwrap();
+    /// assert_eq!(cfg.display.tick_period_ms, 100);
+    ///
+    /// assert!(ECConfig::from_toml(r#"
+    ///         [sync]
+    ///         ntp_servers = [ "ntp.example" ]
+    ///         [display]
+    ///         tick_period_ms = 300"#).is_err());
+    ///
+    /// assert!(ECConfig::from_toml(r#"
+    ///         [sync]
+    ///         ntp_servers = [ "ntp.example" ]
+    ///         [ticker]
+    ///         slew_rate = 0.5"#).is_err());
+    ///
+    /// assert!(ECConfig::from_toml(r#"
+    ///         [sync]
+    ///         ntp_servers = []"#).is_err());
+    ///
+    /// assert!(ECConfig::from_toml(r#"
+    ///         [sync]
+    ///         ntp_servers = [ "ntp.example" ]
+    ///         audit_interval = 0.0"#).is_err());
     /// ```
     pub fn from_toml(s: &str) -> Result<ECConfig, ConfigReadError> {
-        toml::from_str::<ECConfig>(s)
-            .map_err(|e| ConfigReadError::TomlError(e))
+        let cfg = toml::from_str::<ECConfig>(s)
+                    .map_err(ConfigReadError::TomlError)?;
+        cfg.sync.validate()?;
+        cfg.display.validate()?;
+        cfg.ticker.validate()?;
+
+        Ok(cfg)
+    }
+
+    /// A commented configuration file containing the default value of each setting
+    ///
+    /// # Example
+    /// ```
+    /// use eng_clock::{ config::ECConfig, display::DisplayFormat, rt::SchedPolicy };
+    /// let cfg = ECConfig::from_toml(&ECConfig::default_toml()).unwrap();
+    /// assert_eq!(cfg.sync.ntp_servers, ECConfig::default().sync.ntp_servers);
+    /// assert_eq!(cfg.sync.event_log_path, None);
+    /// assert_eq!(cfg.sync.event_log_keep, 5);
+    /// assert_eq!(cfg.estimator, ECConfig::default().estimator);
+    /// assert_eq!(cfg.display.tick_period_ms, 250);
+    /// assert_eq!(cfg.display.format, DisplayFormat::default());
+    /// assert_eq!(cfg.ticker.sched_policy, SchedPolicy::default());
+    /// ```
+    pub fn default_toml() -> String {
+        let cfg = ECConfig::default();
+        let servers = cfg.sync.ntp_servers.iter()
+                                          .map(|s| format!("    {:?}", s))
+                                          .collect::<Vec<String>>()
+                                          .join(",\n");
+
+        format!(r#"# Configuration settings for eng-clock
+
+[sync]
+# NTP servers, chosen at random for each request
+ntp_servers = [
+{servers}
+]
+
+# The desired margin of error in the clock-offset, in seconds
+target_precision = {precision:?}
+
+# The minimum time interval between NTP requests, in seconds
+wakeup_interval = {wakeup:?}
+
+# An optional file to which every NTP exchange is appended as JSON-Lines
+# record_path = "/var/tmp/eng-clock.jsonl"
+
+# Whether to re-poll NTP servers promptly when network addresses or routes change
+watch_network = {watch}
+
+# An optional file to which polls, offset estimates and resets are appended as JSON-Lines
+# event_log_path = "/var/tmp/eng-clock-events.jsonl"
+
+# The size in bytes above which the event log is rotated, or zero for no limit
+event_log_max_bytes = {log_bytes}
+
+# The number of rotated event logs retained (as event_log_path.1, .2, ...)
+event_log_keep = {log_keep}
+
+# An optional file to which hash-chained records of the clock-offset are appended
+# audit_log_path = "/var/lib/eng-clock/audit.jsonl"
+
+# The interval between audit records, in seconds
+audit_interval = {audit_itvl:?}
+
+# The largest permitted divergence of the local clock from UTC, in seconds
+audit_tolerance = {audit_tol:?}
+
+# An optional file containing a secret key with which audit records are signed
+# audit_key_path = "/etc/eng-clock/audit.key"
+
+
+[estimator]
+# The initial uncertainty in the clock-offset, in seconds
+prior_stddev = {prior:?}
+
+# The diffusive growth rate of the offset uncertainty, in seconds per square-root day
+diffusivity = {diffusivity:?}
+
+# The assumed ratio between offset error and NTP round-trip time
+rtt_error_factor = {rtt_factor:?}
+
+# Whether to learn each server's relationship between error and round-trip time
+calibrate_rtt = {calibrate}
+
+# The additional uncertainty in the clock-offset after system suspension, in seconds
+resume_stddev = {resume:?}
+
+
+[display]
+# The time interval between screen updates, in milliseconds (a divisor of 1000)
+tick_period_ms = {tick_period}
+
+# The resolution of the time of day: "seconds", "tenths" or "hundredths"
+format = "seconds"
+
+# Treatment of digits finer than the clock uncertainty: "show", "grey" or "hide"
+insignificant_digits = "grey"
+
+
+[ticker]
+# How the ticker sleeps until each tick: "relative" or "absolute"
+wait = "relative"
+
+# Microseconds of busy-waiting before each tick, when using absolute waits
+spin_us = {spin}
+
+# The largest rate at which offset changes are phased in, or zero to step immediately
+slew_rate = {slew:?}
+
+# Offset changes above this many milliseconds are always stepped
+step_threshold_ms = {step}
+
+# Scheduling policy for the ticker thread: "other", "fifo" or "rr"
+sched_policy = "other"
+
+# The static priority used with a real-time scheduling policy
+sched_priority = {priority}
+
+# CPUs on which the ticker thread may run, or empty for no restriction
+cpu_affinity = []
+
+# Whether all process memory should be locked into RAM
+lock_memory = {lock}
+"#,
+            servers = servers,
+            precision = cfg.sync.target_precision,
+            wakeup = cfg.sync.wakeup_interval,
+            watch = cfg.sync.watch_network,
+            log_bytes = cfg.sync.event_log_max_bytes,
+            log_keep = cfg.sync.event_log_keep,
+            audit_itvl = cfg.sync.audit_interval,
+            audit_tol = cfg.sync.audit_tolerance,
+            prior = cfg.estimator.prior_stddev,
+            diffusivity = cfg.estimator.diffusivity,
+            rtt_factor = cfg.estimator.rtt_error_factor,
+            calibrate = cfg.estimator.calibrate_rtt,
+            resume = cfg.estimator.resume_stddev,
+            tick_period = cfg.display.tick_period_ms,
+            spin = cfg.ticker.spin_us,
+            slew = cfg.ticker.slew_rate,
+            step = cfg.ticker.step_threshold_ms,
+            priority = cfg.ticker.sched_priority,
+            lock = cfg.ticker.lock_memory)
     }
 
     /// Read TOML configuration settings from a supplied filesystem path
@@ -112,16 +624,71 @@ impl ECConfig {
         ECConfig::from_toml(&doc)
     }
 
-    /// Read TOML configuration settings from a well-known location
-    pub fn from_user_config() -> Result<ECConfig, ConfigReadError> {
+    /// The well-known location of the per-user configuration file
+    pub fn user_config_path() -> Result<PathBuf, ConfigReadError> {
         match dirs::config_dir() {
             Some(mut path) => {
                 path.push(ECConfig::CFG_FILENAME);
-                ECConfig::from_path(&path)
+                Ok(path)
             },
             None => Err(ConfigReadError::UnknownHome)
         }
     }
+
+    /// Read TOML configuration settings from a well-known location
+    pub fn from_user_config() -> Result<ECConfig, ConfigReadError> {
+        ECConfig::from_path(&ECConfig::user_config_path()?)
+    }
+}
+
+
+
+/// Settings supplied separately from the configuration file, e.g. on the command line,
+/// which take precedence over it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConfigOverrides {
+    /// NTP hostnames to use in place of those configured, unless empty
+    pub ntp_servers: Vec<String>,
+
+    /// The desired margin of error in the clock-offset, in seconds
+    pub target_precision: Option<f32>,
+
+    /// The minimum time interval between NTP requests, in seconds
+    pub wakeup_interval: Option<f32>
+}
+
+impl ConfigOverrides {
+    /// Replace any settings for which an override has been supplied
+    ///
+    /// # Example
+    /// ```
+    /// use eng_clock::config::{ ConfigOverrides, ECConfig };
+    /// let mut cfg = ECConfig::default();
+    /// let overrides = ConfigOverrides { ntp_servers: vec![ "ntp.example".into() ],
+    ///                                   wakeup_interval: Some(5.0),
+    ///                                   ..ConfigOverrides::default() };
+    /// overrides.apply(&mut cfg).unwrap();
+    /// assert_eq!(cfg.sync.ntp_servers, [ "ntp.example" ]);
+    /// assert_eq!(cfg.sync.wakeup_interval, 5.0);
+    /// assert_eq!(cfg.sync.target_precision, 0.03);
+    ///
+    /// assert!(ConfigOverrides { wakeup_interval: Some(-1.0),
+    ///                           ..ConfigOverrides::default() }
+    ///             .apply(&mut cfg).is_err());
+    /// ```
+    pub fn apply(&self, cfg: &mut ECConfig) -> Result<(), ConfigReadError> {
+        if !self.ntp_servers.is_empty() {
+            cfg.sync.ntp_servers = self.ntp_servers.clone();
+        }
+        if let Some(precision) = self.target_precision {
+            cfg.sync.target_precision = precision;
+        }
+        if let Some(interval) = self.wakeup_interval {
+            cfg.sync.wakeup_interval = interval;
+        }
+
+        cfg.sync.validate()
+    }
 }
 
 // (C)Copyright 2023, RW Penney
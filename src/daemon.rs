@@ -0,0 +1,259 @@
+/*
+ *  Headless service mode for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use std::{
+    os::unix::net::UnixDatagram,
+    process::ExitCode,
+    sync::mpsc,
+    time::{ Duration, Instant } };
+use crate::{
+    OffsetEvent, Timestamp, UImessage, utc_now,
+    config::{ ConfigReadError, ECConfig },
+    diag::TickStats,
+    workers::{ Signal, Workers, install_signal_handlers, take_signal } };
+
+
+/// Settings of the headless service which are not part of the configuration file
+#[derive(Clone, Debug)]
+pub struct DaemonOptions {
+    /// The interval between status reports
+    pub status_interval: Duration
+}
+
+impl Default for DaemonOptions {
+    fn default() -> DaemonOptions {
+        DaemonOptions { status_interval: Duration::from_secs(60) }
+    }
+}
+
+
+/// Client of the systemd service-notification protocol, as used by "Type=notify" units
+pub struct SystemdNotifier {
+    socket: Option<(UnixDatagram, std::os::unix::net::SocketAddr)>,
+
+    /// The interval within which the service manager expects watchdog pings
+    watchdog: Option<Duration>
+}
+
+impl SystemdNotifier {
+    /// Connect to the socket named by $NOTIFY_SOCKET, if any
+    pub fn from_env() -> SystemdNotifier {
+        let socket = std::env::var("NOTIFY_SOCKET").ok()
+                        .and_then(|path| SystemdNotifier::parse_address(&path))
+                        .and_then(|addr| UnixDatagram::unbound().ok()
+                                            .map(|skt| ( skt, addr )));
+        let watchdog = parse_watchdog(std::env::var("WATCHDOG_USEC").ok().as_deref(),
+                                      std::env::var("WATCHDOG_PID").ok().as_deref(),
+                                      std::process::id());
+
+        SystemdNotifier { socket, watchdog }
+    }
+
+    fn parse_address(path: &str) -> Option<std::os::unix::net::SocketAddr> {
+        match path.strip_prefix('@') {
+            #[cfg(target_os = "linux")]
+            Some(name) => {
+                use std::os::linux::net::SocketAddrExt;
+                std::os::unix::net::SocketAddr::from_abstract_name(name).ok()
+            },
+            #[cfg(not(target_os = "linux"))]
+            Some(_) => None,
+            None => std::os::unix::net::SocketAddr::from_pathname(path).ok()
+        }
+    }
+
+    /// Send a newline-separated list of VARIABLE=value assignments
+    pub fn notify(&self, state: &str) {
+        if let Some((skt, addr)) = &self.socket {
+            if let Err(e) = skt.send_to_addr(state.as_bytes(), addr) {
+                log::warn!("Failed to notify service manager - {:?}", e);
+            }
+        }
+    }
+
+    /// The interval between watchdog pings, being half the service manager's timeout
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        self.watchdog.map(|timeout| timeout / 2)
+    }
+}
+
+
+/// Interpret the watchdog timeout set by the service manager,
+/// provided it is directed at the given process
+pub fn parse_watchdog(usec: Option<&str>, pid: Option<&str>, own_pid: u32) -> Option<Duration> {
+    if let Some(pid) = pid {
+        if pid.trim().parse::<u32>().ok() != Some(own_pid) {
+            return None;
+        }
+    }
+
+    usec?.trim().parse::<u64>().ok()
+         .filter(|&us| us > 0)
+         .map(Duration::from_micros)
+}
+
+
+/// One-line summary of the current clock-offset and tick statistics
+pub fn status_line(now: Timestamp, offset: Option<&OffsetEvent>, stats: &TickStats) -> String {
+    let offs_txt = match offset {
+        Some(offs) => format!("offset: {:+.3}ms ± {:.3}ms",
+                              offs.avg_offset.num_microseconds().unwrap_or(0) as f64 * 1e-3,
+                              offs.stddev_offset * 1e3),
+        None => String::from("offset: unknown")
+    };
+
+    format!("{}  {}  ticks: {}  missed: {}  max wake error: {}µs",
+            now.format("%Y-%m-%dT%H:%M:%S%.3fZ"), offs_txt,
+            stats.ticks, stats.missed,
+            stats.jitter.max_error.num_microseconds().unwrap_or(i64::MAX))
+}
+
+
+/// The CLOCK_MONOTONIC time, in microseconds, as required by systemd reload notifications
+fn monotonic_usec() -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+
+    ts.tv_sec as u64 * 1_000_000 + ts.tv_nsec as u64 / 1000
+}
+
+
+/// Run the ticker and offset-estimator without any display, until terminated,
+/// reloading the configuration on SIGHUP and reporting status periodically
+pub fn run<F>(load_config: F, opts: &DaemonOptions) -> ExitCode
+        where F: Fn() -> Result<ECConfig, ConfigReadError> {
+    /// The longest time spent waiting for events before checking for signals
+    const POLL: Duration = Duration::from_millis(200);
+
+    install_signal_handlers(&[ Signal::Terminate, Signal::Reload ]);
+    let notifier = SystemdNotifier::from_env();
+    let watchdog = notifier.watchdog_interval();
+
+    let mut workers = match load_config() {
+        Ok(cfg) => Workers::start(&cfg),
+        Err(e) => {
+            log::error!("Failed to read configuration - {:?}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut stats = TickStats::new();
+    let mut offset: Option<OffsetEvent> = None;
+    let mut ready = false;
+    let mut last_tick = Instant::now();
+    let mut next_status = Instant::now() + opts.status_interval;
+    let mut next_watchdog = Instant::now();
+
+    loop {
+        if take_signal(Signal::Terminate) {
+            notifier.notify("STOPPING=1");
+            workers.stop();
+            log::info!("Terminating");
+            return ExitCode::SUCCESS;
+        }
+
+        if take_signal(Signal::Reload) {
+            match load_config() {
+                Ok(cfg) => {
+                    notifier.notify(&format!("RELOADING=1\nMONOTONIC_USEC={}",
+                                             monotonic_usec()));
+                    workers.stop();
+                    workers = Workers::start(&cfg);
+                    ( ready, offset ) = ( false, None );
+                    stats.resume();
+                    log::info!("Reloaded configuration");
+                },
+                Err(e) => log::error!("Failed to reload configuration, retaining previous settings - {:?}", e)
+            }
+        }
+
+        match workers.events.recv_timeout(POLL) {
+            Ok(UImessage::Tick(tick)) => {
+                stats.add_tick(&tick);
+                last_tick = Instant::now();
+            },
+            Ok(UImessage::Offset(offs)) => {
+                offset = Some(offs);
+                if !ready {
+                    notifier.notify("READY=1");
+                    ready = true;
+                }
+            },
+            Ok(UImessage::Calibration(_)) | Ok(UImessage::Realtime(_))
+                | Ok(UImessage::Poll(_)) => {},
+            Err(mpsc::RecvTimeoutError::Timeout) => {},
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                log::error!("Clock threads have stopped unexpectedly");
+                return ExitCode::FAILURE;
+            }
+        }
+
+        let now = Instant::now();
+        if now >= next_status {
+            let line = status_line(utc_now(), offset.as_ref(), &stats);
+            println!("{}", line);
+            notifier.notify(&format!("STATUS={}", line));
+            next_status = now + opts.status_interval;
+        }
+
+        // Only reassure the watchdog while ticks are still flowing:
+        if let Some(itvl) = watchdog {
+            if now >= next_watchdog && now.duration_since(last_tick) < itvl {
+                notifier.notify("WATCHDOG=1");
+                next_watchdog = now + itvl;
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    #[test]
+    fn watchdog_settings() {
+        assert_eq!(parse_watchdog(Some("30000000"), None, 17),
+                   Some(Duration::from_secs(30)));
+        assert_eq!(parse_watchdog(Some("30000000"), Some("17"), 17),
+                   Some(Duration::from_secs(30)));
+        assert_eq!(parse_watchdog(Some("30000000"), Some("18"), 17), None);
+        assert_eq!(parse_watchdog(Some("0"), None, 17), None);
+        assert_eq!(parse_watchdog(None, None, 17), None);
+    }
+
+    #[test]
+    fn notification() {
+        let path = temp_path("notify");
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixDatagram::bind(&path).unwrap();
+        let notifier = SystemdNotifier {
+            socket: Some(( UnixDatagram::unbound().unwrap(),
+                           SystemdNotifier::parse_address(path.to_str().unwrap())
+                                .unwrap() )),
+            watchdog: Some(Duration::from_secs(10)) };
+
+        notifier.notify("READY=1");
+        let mut buff = [0u8; 64];
+        let len = listener.recv(&mut buff).unwrap();
+        assert_eq!(&buff[..len], b"READY=1");
+        assert_eq!(notifier.watchdog_interval(), Some(Duration::from_secs(5)));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn status_format() {
+        let offs = OffsetEvent { avg_offset: chrono::Duration::microseconds(-12_345),
+                                 stddev_offset: 0.0021 };
+        let line = status_line(mk_time(3600, (250, 0, 0)), Some(&offs), &TickStats::new());
+
+        assert_eq!(line, "1991-07-10T01:00:00.250Z  offset: -12.345ms ± 2.100ms  ticks: 0  missed: 0  max wake error: 0µs");
+        assert!(status_line(mk_time(0, (0, 0, 0)), None, &TickStats::new())
+                    .contains("offset: unknown"));
+    }
+}
+
+// (C)Copyright 2026, RW Penney
@@ -0,0 +1,179 @@
+/*
+ *  Tick-timing diagnostics for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use crate::TickEvent;
+
+
+/// Upper limits of the histogram bins of late wakeups, in microseconds
+pub const JITTER_BIN_EDGES_US: [i64; 7] = [ 10, 30, 100, 300, 1_000, 3_000, 10_000 ];
+
+
+/// Histogram of the difference between actual and intended Ticker wakeup times
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JitterHistogram {
+    /// The number of wakeups occurring before their intended time
+    pub early: u64,
+
+    /// The number of late wakeups within each bin of JITTER_BIN_EDGES_US,
+    /// plus a final overflow bin
+    pub late: [u64; JITTER_BIN_EDGES_US.len() + 1],
+
+    /// The largest absolute wakeup error seen so far
+    pub max_error: chrono::Duration
+}
+
+impl JitterHistogram {
+    pub fn add_sample(&mut self, error: chrono::Duration) {
+        let us = error.num_microseconds().unwrap_or(i64::MAX);
+
+        if us < 0 {
+            self.early += 1;
+        } else {
+            let bin = JITTER_BIN_EDGES_US.iter().position(|&edge| us < edge)
+                                         .unwrap_or(JITTER_BIN_EDGES_US.len());
+            self.late[bin] += 1;
+        }
+
+        self.max_error = self.max_error.max(error.abs());
+    }
+
+    /// The total number of samples recorded
+    pub fn count(&self) -> u64 {
+        self.early + self.late.iter().sum::<u64>()
+    }
+
+    /// The textual label of each late-wakeup bin
+    pub fn bin_labels() -> Vec<String> {
+        let fmt_us = |us: i64| if us >= 1000 { format!("{}ms", us / 1000) }
+                               else { format!("{}µs", us) };
+        let mut labels: Vec<String> =
+            JITTER_BIN_EDGES_US.iter().map(|&e| format!("<{}", fmt_us(e))).collect();
+        labels.push(format!("≥{}", fmt_us(*JITTER_BIN_EDGES_US.last().unwrap())));
+
+        labels
+    }
+}
+
+
+/// Accumulated statistics on the timing and continuity of clock ticks
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TickStats {
+    /// The distribution of Ticker wakeup errors
+    pub jitter: JitterHistogram,
+
+    /// The number of ticks received
+    pub ticks: u64,
+
+    /// The number of tick identifiers skipped over
+    pub missed: u64,
+
+    /// The number of discontinuities in which one or more ticks were skipped
+    pub gaps: u64,
+
+    /// The number of ticks repeating, or preceding, an earlier identifier
+    pub duplicates: u64,
+
+    /// The identifier of the most recent tick
+    pub last_id: Option<i64>
+}
+
+impl TickStats {
+    pub fn new() -> TickStats {
+        TickStats::default()
+    }
+
+    /// Incorporate a tick, checking its identifier against its predecessor
+    pub fn add_tick(&mut self, event: &TickEvent) {
+        self.ticks += 1;
+        self.jitter.add_sample(event.wake_error);
+
+        if let Some(last) = self.last_id {
+            let step = event.tick_id - last;
+
+            if step > 1 {
+                self.missed += (step - 1) as u64;
+                self.gaps += 1;
+            } else if step < 1 {
+                self.duplicates += 1;
+            }
+        }
+
+        // Retain the later identifier, so a stray duplicate doesn't also count as a gap:
+        self.last_id = Some(self.last_id.map_or(event.tick_id,
+                                                |last| last.max(event.tick_id)));
+    }
+
+    /// Forget the previous tick identifier, so that a deliberate
+    /// pause in ticking is not counted as missed ticks
+    pub fn resume(&mut self) {
+        self.last_id = None;
+    }
+
+    /// Render a multi-line textual summary
+    pub fn report(&self) -> String {
+        let mut txt = format!("ticks: {}  missed: {} (in {} gaps)  duplicates: {}\n",
+                              self.ticks, self.missed, self.gaps, self.duplicates);
+        txt += &format!("wake error: max {}µs, early {}\n",
+                        self.jitter.max_error.num_microseconds().unwrap_or(i64::MAX),
+                        self.jitter.early);
+
+        for (label, count) in JitterHistogram::bin_labels().iter()
+                                    .zip(self.jitter.late.iter()) {
+            txt += &format!("{:>8}: {}\n", label, count);
+        }
+
+        txt
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    fn tick(tick_id: i64, wake_us: i64) -> TickEvent {
+        TickEvent { tick_id,
+                    wake_error: chrono::Duration::microseconds(wake_us),
+                    ..mk_tick(0, 0) }
+    }
+
+    #[test]
+    fn histogram_bins() {
+        let mut hist = JitterHistogram::default();
+
+        for us in [ -5, 0, 9, 10, 250, 999, 1000, 20_000 ] {
+            hist.add_sample(chrono::Duration::microseconds(us));
+        }
+
+        assert_eq!(hist.early, 1);
+        assert_eq!(hist.late, [ 2, 1, 0, 1, 1, 1, 0, 1 ]);
+        assert_eq!(hist.count(), 8);
+        assert_eq!(hist.max_error, chrono::Duration::microseconds(20_000));
+        assert_eq!(JitterHistogram::bin_labels().len(), hist.late.len());
+    }
+
+    #[test]
+    fn tick_continuity() {
+        let mut stats = TickStats::new();
+
+        for id in [ 100, 101, 102, 105, 106, 106, 104, 107, 117 ] {
+            stats.add_tick(&tick(id, 50));
+        }
+
+        assert_eq!(stats.ticks, 9);
+        assert_eq!(stats.missed, 2 + 9);
+        assert_eq!(stats.gaps, 2);
+        assert_eq!(stats.duplicates, 2);
+        assert_eq!(stats.last_id, Some(117));
+        assert!(stats.report().starts_with("ticks: 9  missed: 11 (in 2 gaps)"));
+
+        stats.resume();
+        stats.add_tick(&tick(400, 50));
+        assert_eq!(( stats.missed, stats.gaps ), ( 11, 2 ));
+    }
+}
+
+// (C)Copyright 2026, RW Penney
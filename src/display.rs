@@ -0,0 +1,212 @@
+/*
+ *  Text formatting of clock displays for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use serde::Deserialize;
+use crate::{ TickEvent, Timestamp };
+
+
+/// Glyphs indicating which quarter of the current second has been reached
+pub const PHASE_CHARS: [char; 4] = [ '=', '.', ':', '\'' ];
+
+
+/// The resolution with which the time of day is shown
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayFormat {
+    /// Whole seconds, as HH:MM:SS
+    #[default]
+    Seconds,
+
+    /// Tenths of a second, as HH:MM:SS.s
+    Tenths,
+
+    /// Hundredths of a second, as HH:MM:SS.ss
+    Hundredths
+}
+
+impl DisplayFormat {
+    /// The number of decimal places shown after the seconds
+    pub fn subsec_digits(self) -> u32 {
+        match self {
+            DisplayFormat::Seconds =>       0,
+            DisplayFormat::Tenths =>        1,
+            DisplayFormat::Hundredths =>    2
+        }
+    }
+
+    /// Render the time of day, truncated to the resolution of this format
+    pub fn format(self, t: Timestamp) -> String {
+        let hms = t.format("%H:%M:%S").to_string();
+
+        match self.subsec_digits() {
+            0 => hms,
+            n => format!("{}.{}", hms, subsec_text(t, n))
+        }
+    }
+
+    /// Render the time of day, split into the leading text which is meaningful
+    /// given the uncertainty (in seconds) in the clock-offset,
+    /// and any trailing digits which are not
+    pub fn format_split(self, t: Timestamp, stddev: f32) -> (String, String) {
+        let full = self.format(t);
+        let n_digits = self.subsec_digits();
+        let n_sig = significant_digits(stddev, n_digits);
+
+        // Retain the decimal point only if followed by a significant digit:
+        let split = full.len() - (n_digits - n_sig) as usize
+                        - if n_sig == 0 && n_digits > 0 { 1 } else { 0 };
+        let (sig, insig) = full.split_at(split);
+
+        ( String::from(sig), String::from(insig) )
+    }
+}
+
+
+/// The treatment of sub-second digits which are smaller than the clock uncertainty
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DigitSuppression {
+    /// Show all digits identically
+    Show,
+
+    /// Show insignificant digits in a subdued colour
+    #[default]
+    Grey,
+
+    /// Omit insignificant digits
+    Hide
+}
+
+
+/// The number of sub-second digits, up to a given limit, whose values
+/// are meaningful given the uncertainty (in seconds) in the clock-offset
+pub fn significant_digits(stddev: f32, max_digits: u32) -> u32 {
+    (1 ..= max_digits).take_while(|&k| stddev <= 0.5 * 10f32.powi(-(k as i32)))
+                      .count() as u32
+}
+
+
+/// The leading decimal digits of the fractional part of a timestamp's seconds
+pub fn subsec_text(t: Timestamp, digits: u32) -> String {
+    let frac = t.timestamp_subsec_micros() / 10u32.pow(6 - digits);
+    format!("{:0width$}", frac, width = digits as usize)
+}
+
+
+/// The display refresh interval assumed when the frame clock has no history, in microseconds
+pub const DEFAULT_REFRESH_US: i64 = 16_667;
+
+
+/// Whether a frame presented at the given time is closer to a target time
+/// than the following frame, one refresh interval later (all in microseconds)
+pub fn frame_is_closest(presentation_us: i64, refresh_us: i64, target_us: i64) -> bool {
+    presentation_us + refresh_us / 2 >= target_us
+}
+
+
+/// The glyph indicating the sub-second phase of a clock tick,
+/// or which of four successive ticks this is, if ticks are a second apart
+pub fn phase_char(event: &TickEvent) -> char {
+    let quarter = if event.period_us >= 1_000_000 {
+        event.tick_id.rem_euclid(4) as u32
+    } else {
+        event.t_nominal.timestamp_subsec_micros() / 250_000
+    };
+    PHASE_CHARS[quarter.min(3) as usize]
+}
+
+
+/// Whether a clock tick is the first within its second,
+/// such that once-per-second updates happen for any tick period
+pub fn is_first_in_second(event: &TickEvent) -> bool {
+    (event.t_nominal.timestamp_subsec_micros() as i64) < event.period_us
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    #[test]
+    fn time_formats() {
+        let t = mk_time(3723, (456, 789, 0));
+
+        assert_eq!(DisplayFormat::Seconds.format(t), "01:02:03");
+        assert_eq!(DisplayFormat::Tenths.format(t), "01:02:03.4");
+        assert_eq!(DisplayFormat::Hundredths.format(t), "01:02:03.45");
+        assert_eq!(subsec_text(mk_time(0, (7, 0, 0)), 3), "007");
+    }
+
+    #[test]
+    fn significance() {
+        assert_eq!(significant_digits(f32::INFINITY, 2), 0);
+        assert_eq!(significant_digits(0.2, 2), 0);
+        assert_eq!(significant_digits(0.03, 2), 1);
+        assert_eq!(significant_digits(0.004, 2), 2);
+        assert_eq!(significant_digits(0.004, 1), 1);
+        assert_eq!(significant_digits(1e-6, 0), 0);
+    }
+
+    #[test]
+    fn split_formats() {
+        let t = mk_time(3723, (456, 789, 0));
+        let split = |fmt: DisplayFormat, sd| fmt.format_split(t, sd);
+
+        assert_eq!(split(DisplayFormat::Seconds, 1.0),
+                   ( "01:02:03".into(), "".into() ));
+        assert_eq!(split(DisplayFormat::Hundredths, 1.0),
+                   ( "01:02:03".into(), ".45".into() ));
+        assert_eq!(split(DisplayFormat::Hundredths, 0.02),
+                   ( "01:02:03.4".into(), "5".into() ));
+        assert_eq!(split(DisplayFormat::Hundredths, 0.001),
+                   ( "01:02:03.45".into(), "".into() ));
+        assert_eq!(split(DisplayFormat::Tenths, 0.001),
+                   ( "01:02:03.4".into(), "".into() ));
+    }
+
+    #[test]
+    fn frame_selection() {
+        let refresh = 16_000;
+
+        assert!(frame_is_closest(1_000_000, refresh, 1_000_000));
+        assert!(frame_is_closest(1_000_000, refresh, 1_007_999));
+        assert!(frame_is_closest(1_000_000, refresh, 1_008_000));
+        assert!(!frame_is_closest(1_000_000, refresh, 1_008_001));
+        assert!(!frame_is_closest(1_000_000, refresh, 1_050_000));
+        assert!(frame_is_closest(1_000_000, refresh, 900_000));
+    }
+
+    fn tick(secs: i32, ms: i32, period_ms: i64) -> TickEvent {
+        TickEvent { tick_id: (secs as i64 * 1000 + ms as i64) / period_ms,
+                    period_us: period_ms * 1000,
+                    ..mk_tick(secs, ms) }
+    }
+
+    #[test]
+    fn phases() {
+        assert_eq!(phase_char(&tick(12, 0, 10)), '=');
+        assert_eq!(phase_char(&tick(12, 240, 10)), '=');
+        assert_eq!(phase_char(&tick(12, 250, 10)), '.');
+        assert_eq!(phase_char(&tick(12, 600, 10)), ':');
+        assert_eq!(phase_char(&tick(12, 990, 10)), '\'');
+
+        let slow: Vec<char> = (12 .. 16).map(|s| phase_char(&tick(s, 0, 1000))).collect();
+        assert_eq!(slow, PHASE_CHARS);
+    }
+
+    #[test]
+    fn second_boundaries() {
+        for period in [ 10, 200, 250, 1000 ] {
+            let firsts = (0 .. 3000 / period as i32)
+                            .map(|n| n * period as i32)
+                            .filter(|ms| is_first_in_second(&tick(ms / 1000, ms % 1000, period)))
+                            .count();
+            assert_eq!(firsts, 3, "period={}ms", period);
+        }
+    }
+}
+
+// (C)Copyright 2026, RW Penney
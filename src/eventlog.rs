@@ -0,0 +1,397 @@
+/*
+ *  Structured logging of clock-offset estimation for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use serde::{ Deserialize, Serialize };
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{ BufRead, Write },
+    path::{ Path, PathBuf },
+    sync::{ Arc, Mutex } };
+use crate::{
+    OffsetEvent, Timestamp,
+    config::SyncConfig,
+    ntp::{ NtpError, NtpExchange },
+    sync::TimeSource };
+
+
+/// The cause of the offset estimator abandoning its steady polling schedule
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetReason {
+    /// The system resumed from suspension, so the local clock may have jumped
+    Suspension,
+
+    /// Network addresses or routes changed, so server paths may have changed
+    NetworkChange
+}
+
+
+/// An occurrence within the offset estimator
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum LogEvent {
+    /// A single NTP request, with times in seconds
+    Poll {
+        server: String,
+
+        /// The network address which responded
+        address: Option<String>,
+
+        rtt: Option<f64>,
+
+        /// The correction to be added to the local clock
+        offset: Option<f64>,
+
+        /// The reason for the request failing
+        error: Option<NtpError>
+    },
+
+    /// A new estimate of the clock-offset
+    Offset(OffsetEvent),
+
+    /// A return to rapid polling, such that the offset estimate may change abruptly
+    Reset { reason: ResetReason }
+}
+
+impl LogEvent {
+    /// Summarize the outcome of an NTP request
+    pub fn poll(server: &str, result: &Result<NtpExchange, NtpError>) -> LogEvent {
+        match result {
+            Ok(exch) => LogEvent::Poll {
+                server: String::from(server),
+                address: Some(exch.address.to_string()),
+                rtt: Some(exch.roundtrip()),
+                offset: Some(exch.offset()),
+                error: None },
+            Err(e) => LogEvent::Poll {
+                server: String::from(server),
+                address: None, rtt: None, offset: None,
+                error: Some(*e) }
+        }
+    }
+}
+
+
+/// A timestamped event, as written on each line of the event log
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// The (uncorrected) local time at which the event was recorded
+    pub time: Timestamp,
+
+    #[serde(flatten)]
+    pub event: LogEvent
+}
+
+impl LogEntry {
+    /// Read a sequence of entries from a JSON-Lines document
+    pub fn read_all<R: BufRead>(rdr: R) -> std::io::Result<Vec<LogEntry>> {
+        let mut entries = vec![];
+
+        for line in rdr.lines() {
+            let line = line?;
+            if line.trim().is_empty() { continue; }
+
+            entries.push(serde_json::from_str(&line)
+                            .map_err(|e| std::io::Error::new(
+                                            std::io::ErrorKind::InvalidData, e))?);
+        }
+
+        Ok(entries)
+    }
+
+    /// Read a sequence of entries from a JSON-Lines file
+    pub fn from_path(path: &Path) -> std::io::Result<Vec<LogEntry>> {
+        let file = File::open(path)?;
+        LogEntry::read_all(std::io::BufReader::new(file))
+    }
+}
+
+
+/// A file which is renamed with a numerical suffix once it exceeds a size limit
+struct RotatingFile {
+    path: PathBuf,
+
+    /// The size above which the file is rotated, or zero for no limit
+    max_bytes: u64,
+
+    /// The number of rotated files retained
+    keep: u32,
+
+    file: Option<File>,
+    size: u64,
+
+    /// Whether a write failure has already been reported
+    failed: bool
+}
+
+impl RotatingFile {
+    fn numbered(&self, idx: u32) -> PathBuf {
+        let mut name = OsString::from(self.path.as_os_str());
+        name.push(format!(".{}", idx));
+
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+
+        for idx in (1 .. self.keep).rev() {
+            match std::fs::rename(self.numbered(idx), self.numbered(idx + 1)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+
+        let result = if self.keep > 0 {
+            std::fs::rename(&self.path, self.numbered(1))
+        } else {
+            std::fs::remove_file(&self.path)
+        };
+
+        // A live file removed by another process needs no rotation, just reopening:
+        match result {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            other => other
+        }
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        if self.max_bytes > 0 && self.size > 0
+                && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let file = std::fs::OpenOptions::new().create(true)
+                                                      .append(true).open(&self.path)?;
+                self.size = file.metadata()?.len();
+                self.file.insert(file)
+            }
+        };
+
+        file.write_all(line)?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+}
+
+
+/// Shareable writer of timestamped events to a JSON-Lines file, with size-based rotation
+#[derive(Clone)]
+pub struct EventLog {
+    file: Arc<Mutex<RotatingFile>>
+}
+
+impl EventLog {
+    /// The default size above which the log is rotated, in bytes
+    pub const DEFAULT_MAX_BYTES: u64 = 10_000_000;
+
+    /// The default number of rotated logs retained
+    pub const DEFAULT_KEEP: u32 = 5;
+
+    /// Prepare to append to the given file, which will be opened when first written
+    pub fn new(path: &Path, max_bytes: u64, keep: u32) -> EventLog {
+        EventLog {
+            file: Arc::new(Mutex::new(RotatingFile {
+                path: PathBuf::from(path), max_bytes, keep,
+                file: None, size: 0, failed: false }))
+        }
+    }
+
+    /// The event log described by the synchronization settings, if any
+    pub fn from_config(config: &SyncConfig) -> Option<EventLog> {
+        config.event_log_path.as_ref()
+              .map(|path| EventLog::new(path, config.event_log_max_bytes,
+                                        config.event_log_keep))
+    }
+
+    /// Append a single event, reporting only the first failure to do so
+    pub fn record(&self, time: Timestamp, event: LogEvent) {
+        let mut line = serde_json::to_vec(&LogEntry { time, event })
+                            .expect("Log entry should be serializable");
+        line.push(b'\n');
+
+        let mut file = self.file.lock().expect("Event log is poisoned");
+        if let Err(e) = file.write_line(&line) {
+            if !file.failed {
+                log::warn!("Failed to write event log {:?} - {:?}", file.path, e);
+                file.failed = true;
+            }
+        }
+    }
+}
+
+
+/// Wrapper around a time-source which logs the outcome of every NTP exchange
+pub struct LoggingSource<S: TimeSource> {
+    inner: S,
+    log: EventLog
+}
+
+impl<S: TimeSource> LoggingSource<S> {
+    pub fn new(inner: S, log: EventLog) -> LoggingSource<S> {
+        LoggingSource { inner, log }
+    }
+}
+
+impl<S: TimeSource> TimeSource for LoggingSource<S> {
+    fn now(&self) -> Timestamp {
+        self.inner.now()
+    }
+
+    fn ntp_ping(&mut self, server: &str) -> Result<NtpExchange, NtpError> {
+        let result = self.inner.ntp_ping(server);
+        self.log.record(self.inner.now(), LogEvent::poll(server, &result));
+
+        result
+    }
+
+    fn sleep(&mut self, dt: std::time::Duration) {
+        self.inner.sleep(dt)
+    }
+
+    fn choose_server(&mut self, count: usize) -> usize {
+        self.inner.choose_server(count)
+    }
+
+    fn take_suspension(&mut self) -> Option<std::time::Duration> {
+        self.inner.take_suspension()
+    }
+
+    fn take_network_change(&mut self) -> bool {
+        self.inner.take_network_change()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sim::{ Scenario, SimNetwork },
+        sync::OffsetTracker,
+        testing::* };
+
+    #[test]
+    fn entry_format() {
+        let entry = LogEntry {
+            time: mk_time(10, (500, 0, 0)),
+            event: LogEvent::Offset(OffsetEvent {
+                avg_offset: chrono::Duration::microseconds(-1250),
+                stddev_offset: 0.5 }) };
+        let json = serde_json::to_string(&entry).unwrap();
+
+        assert_eq!(json, r#"{"time":"1991-07-10T00:00:10.500Z","event":"offset","avg_offset":-0.00125,"stddev_offset":0.5}"#);
+        assert_eq!(serde_json::from_str::<LogEntry>(&json).unwrap(), entry);
+
+        let reset = LogEntry { time: mk_time(0, (0, 0, 0)),
+                               event: LogEvent::Reset { reason: ResetReason::NetworkChange } };
+        assert!(serde_json::to_string(&reset).unwrap()
+                    .contains(r#""event":"reset","reason":"network_change""#));
+
+        let failure = LogEvent::poll("ntp.example", &Err(NtpError::Timeout));
+        assert!(serde_json::to_string(&failure).unwrap()
+                    .contains(r#""rtt":null,"offset":null,"error":"Timeout""#));
+    }
+
+    #[test]
+    fn rotation() {
+        let path = temp_path("rotation.jsonl");
+        let log = EventLog::new(&path, 400, 2);
+        let files: Vec<PathBuf> = (0 .. 4).map(|idx| match idx {
+                0 => path.clone(),
+                _ => log.file.lock().unwrap().numbered(idx)
+            }).collect();
+        for f in files.iter() { let _ = std::fs::remove_file(f); }
+
+        for idx in 0 .. 40 {
+            log.record(mk_time(idx, (0, 0, 0)),
+                       LogEvent::Reset { reason: ResetReason::Suspension });
+        }
+
+        let sizes: Vec<Option<u64>> = files.iter()
+                                           .map(|f| std::fs::metadata(f).ok().map(|m| m.len()))
+                                           .collect();
+        assert!(sizes[0 .. 3].iter().all(|s| matches!(s, Some(n) if *n <= 400)));
+        assert_eq!(sizes[3], None);
+
+        let latest = LogEntry::from_path(&path).unwrap();
+        let previous = LogEntry::from_path(&files[1]).unwrap();
+        assert_eq!(latest.last().unwrap().time, mk_time(39, (0, 0, 0)));
+        assert_eq!(previous.last().unwrap().time + chrono::Duration::seconds(1),
+                   latest.first().unwrap().time);
+
+        for f in files.iter() { let _ = std::fs::remove_file(f); }
+    }
+
+    #[test]
+    fn rotation_after_removal() {
+        let path = temp_path("removal.jsonl");
+        for keep in [ 0, 1 ] {
+            let log = EventLog::new(&path, 200, keep);
+            let rotated = log.file.lock().unwrap().numbered(1);
+            let _ = std::fs::remove_file(&rotated);
+
+            log.record(mk_time(0, (0, 0, 0)), LogEvent::Reset { reason: ResetReason::Suspension });
+            std::fs::remove_file(&path).unwrap();
+            for idx in 1 .. 10 {
+                log.record(mk_time(idx, (0, 0, 0)),
+                           LogEvent::Reset { reason: ResetReason::Suspension });
+            }
+
+            let latest = LogEntry::from_path(&path).unwrap();
+            assert_eq!(latest.last().unwrap().time, mk_time(9, (0, 0, 0)));
+            assert!(!log.file.lock().unwrap().failed);
+
+            std::fs::remove_file(&path).unwrap();
+            let _ = std::fs::remove_file(&rotated);
+        }
+    }
+
+    #[test]
+    fn logged_polls() {
+        let scen = Scenario::from_toml(r#"
+            duration = 600.0
+            seed = 3
+
+            [oscillator]
+            initial_error = 0.2
+
+            [[servers]]
+            name = "lossy.example"
+            loss = 0.5
+        "#).unwrap();
+        let path = temp_path("polls.jsonl");
+        let _ = std::fs::remove_file(&path);
+
+        let mut src = LoggingSource::new(SimNetwork::new(&scen), EventLog::new(&path, 0, 0));
+        let mut tracker = OffsetTracker::new(&scen.sync_config(), &scen.estimator);
+        for _ in 0 .. 10 {
+            let (_, pause) = tracker.step(&mut src);
+            src.sleep(pause);
+        }
+
+        let entries = LogEntry::from_path(&path).unwrap();
+        let outcomes: Vec<(bool, bool)> = entries.iter().map(|e| match &e.event {
+                LogEvent::Poll { server, rtt, error, .. } => {
+                    assert_eq!(server, "lossy.example");
+                    ( rtt.is_some(), error.is_some() )
+                },
+                _ => panic!("Unexpected event {:?}", e)
+            }).collect();
+
+        assert!(outcomes.contains(&( true, false )));
+        assert!(outcomes.contains(&( false, true )));
+        assert!(entries.windows(2).all(|w| w[0].time <= w[1].time));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+// (C)Copyright 2026, RW Penney
@@ -0,0 +1,367 @@
+/*
+ *  GTK window and widgets for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use gtk::{ gdk, glib };
+use gtk::prelude::*;
+use std::{
+    cell::{ Cell, OnceCell, RefCell },
+    rc::Rc,
+    sync::mpsc,
+    thread };
+
+use eng_clock::{
+    OffsetEvent, TickEvent, TickerMessage, Timestamp, UImessage, UIsender, utc_now,
+    bus::{ EventBus, EventFilter },
+    config::{ DisplayConfig, ECConfig },
+    diag::TickStats,
+    display::{ self, DigitSuppression, DisplayFormat },
+    stats::{ ExpoAvg, RttFit },
+    sync::OffsetEstimator,
+    ticker::Ticker
+};
+
+use crate::format_rtt_fits;
+
+
+/// A clock-tick awaiting display, with presentation times
+/// on the GLib monotonic clock, in microseconds
+struct PendingTick {
+    event: TickEvent,
+
+    /// The intended presentation time
+    target_us: i64,
+
+    /// The presentation time of the first frame after the tick arrived
+    earliest_us: Option<i64>
+}
+
+
+/// Collection of GTK widgets that may need dynamic updates
+#[derive(Clone)]
+struct Widgets {
+    hms_label: gtk::Label,
+    phase_label: gtk::Label,
+    latency_label: gtk::Label,
+    avg_offs_label: gtk::Label,
+    diag_label: gtk::Label,
+
+    avg_latency: Rc<RefCell<ExpoAvg>>,
+    avg_jitter: Rc<RefCell<ExpoAvg>>,
+
+    /// Statistics on tick timing and continuity
+    tick_stats: Rc<RefCell<TickStats>>,
+
+    /// Description of the scheduling policy achieved by the Ticker thread
+    realtime_txt: Rc<RefCell<String>>,
+
+    /// Description of the smoothed error in Ticker wakeup times
+    jitter_txt: Rc<RefCell<String>>,
+
+    /// A tick awaiting a suitable display frame
+    pending_tick: Rc<RefCell<Option<PendingTick>>>,
+
+    /// Channel for reporting display latency back to the Ticker
+    tkr_channel: Rc<OnceCell<mpsc::Sender<TickerMessage>>>,
+
+    /// The resolution with which the time of day is shown
+    format: DisplayFormat,
+
+    /// The treatment of sub-second digits finer than the clock uncertainty
+    suppression: DigitSuppression,
+
+    /// The latest uncertainty in the clock-offset, in seconds
+    stddev_offset: Rc<Cell<f32>>
+}
+
+impl Widgets {
+    pub fn new(root: &gtk::ApplicationWindow, config: &DisplayConfig) -> Widgets {
+        let rtbox = gtk::Box::new(gtk::Orientation::Vertical, 3);
+        root.add(&rtbox);
+
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        rtbox.pack_start(&hbox, false, false, 0);
+
+        let hms_label = gtk::Label::new(None);
+        hms_label.set_halign(gtk::Align::Center);
+        hbox.pack_start(&hms_label, false, false, 2);
+
+        let phase_label = gtk::Label::new(None);
+        phase_label.set_halign(gtk::Align::End);
+        hbox.pack_start(&phase_label, false, false, 6);
+
+        let vbox = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        rtbox.pack_start(&vbox, false, false, 0);
+
+        let avg_offs_label = gtk::Label::new(None);
+        avg_offs_label.set_halign(gtk::Align::Start);
+        vbox.pack_start(&avg_offs_label, false, false, 0);
+
+        let latency_label = gtk::Label::new(None);
+        latency_label.set_halign(gtk::Align::Start);
+        vbox.pack_start(&latency_label, false, false, 0);
+
+        let expander = gtk::Expander::new(Some("Diagnostics"));
+        vbox.pack_start(&expander, false, false, 0);
+
+        let diag_label = gtk::Label::new(None);
+        diag_label.set_halign(gtk::Align::Start);
+        diag_label.set_selectable(true);
+        expander.add(&diag_label);
+
+        Widgets {
+            hms_label,
+            phase_label,
+            avg_offs_label,
+            latency_label,
+            diag_label,
+            avg_latency: Rc::new(RefCell::new(ExpoAvg::new(0.1))),
+            avg_jitter: Rc::new(RefCell::new(ExpoAvg::new(0.1))),
+            tick_stats: Rc::new(RefCell::new(TickStats::new())),
+            realtime_txt: Rc::new(RefCell::new(String::new())),
+            jitter_txt: Rc::new(RefCell::new(String::new())),
+            pending_tick: Rc::new(RefCell::new(None)),
+            tkr_channel: Rc::new(OnceCell::new()),
+            format: config.format,
+            suppression: config.insignificant_digits,
+            stddev_offset: Rc::new(Cell::new(f32::INFINITY))
+        }
+    }
+
+    /// Prepare UI-update communication channel and associated event handlers
+    pub fn init_channel(&self) -> UIsender {
+        let (sender, receiver) =
+            glib::MainContext::channel(glib::PRIORITY_HIGH);
+        let w = self.clone();
+
+        // Wire-up message handlers:
+        receiver.attach(None, move |msg| {
+            match msg {
+                UImessage::Tick(event) =>   w.receive_tick(event),
+                UImessage::Offset(event) => w.receive_offset(event),
+                UImessage::Calibration(fits) => w.receive_calibration(&fits),
+                UImessage::Realtime(status) =>
+                    *w.realtime_txt.borrow_mut() = status.to_string(),
+                UImessage::Poll(_) => {}
+            };
+            glib::Continue(true)
+        });
+
+        sender
+    }
+
+    /// Connect the feedback path through which display latency is reported to the Ticker
+    pub fn set_ticker_channel(&self, channel: mpsc::Sender<TickerMessage>) {
+        self.tkr_channel.set(channel)
+            .expect("Ticker channel should only be connected once");
+    }
+
+    /// Pause the Ticker while the window is iconified or fully obscured
+    ///
+    /// Compositing window managers render every window off-screen, and so rarely
+    /// report obscuration, leaving iconification as the usual cause of pausing.
+    pub fn track_visibility(&self, win: &gtk::ApplicationWindow) {
+        let iconified = Rc::new(Cell::new(false));
+        let obscured = Rc::new(Cell::new(false));
+
+        let notify = {
+            let w = self.clone();
+            let (iconified, obscured) = ( iconified.clone(), obscured.clone() );
+            let visible = Rc::new(Cell::new(true));
+
+            move || {
+                let now_visible = !iconified.get() && !obscured.get();
+                if now_visible == visible.replace(now_visible) { return; }
+
+                if now_visible {
+                    w.tick_stats.borrow_mut().resume();
+                }
+                if let Some(tkr) = w.tkr_channel.get() {
+                    tkr.send(TickerMessage::Visible(now_visible)).unwrap();
+                }
+            }
+        };
+        let notify = Rc::new(notify);
+
+        let n = notify.clone();
+        win.connect_window_state_event(move |_, event| {
+            iconified.set(event.new_window_state()
+                               .contains(gdk::WindowState::ICONIFIED));
+            n();
+            glib::signal::Inhibit(false)
+        });
+
+        // The deprecated "visibility-notify-event" lacks a typed binding:
+        win.add_events(gdk::EventMask::VISIBILITY_NOTIFY_MASK);
+        win.connect_local("visibility-notify-event", false, move |args| {
+            let event = args.get(1)?.get::<gdk::Event>().ok()?;
+            if let Some(vis) = event.downcast_ref::<gdk::EventVisibility>() {
+                obscured.set(vis.state() == gdk::VisibilityState::FullyObscured);
+                notify();
+            }
+            Some(false.to_value())
+        });
+    }
+
+    /// Schedule GUI update after receiving clock-tick from Ticker,
+    /// for the display frame presented closest to the nominal tick time
+    pub fn receive_tick(&self, event: TickEvent) {
+        let avg_jitter = self.avg_jitter.borrow_mut()
+                                        .add_duration(event.wake_error.abs());
+        self.tick_stats.borrow_mut().add_tick(&event);
+        self.jitter_txt.replace(format!("wake jitter: {}µs",
+                                        avg_jitter.num_microseconds().unwrap_or(0)));
+
+        // Without a frame clock (e.g. before the window is realized), show immediately:
+        if self.hms_label.frame_clock().is_none() {
+            self.show_tick(&event, utc_now());
+            return;
+        }
+
+        // The Ticker intended this tick to be displayed one lead-interval after waking,
+        // which can be expressed on the GLib monotonic clock used by frame timings:
+        let t_display = event.t_transmit - event.wake_error + event.lead;
+        let target_us = glib::monotonic_time()
+                            + (t_display - utc_now()).num_microseconds().unwrap_or(0);
+
+        // Any tick still awaiting a frame is superseded, re-using its tick callback:
+        let pending = PendingTick { event, target_us, earliest_us: None };
+        if self.pending_tick.replace(Some(pending)).is_some() {
+            return;
+        }
+
+        let w = self.clone();
+        self.hms_label.add_tick_callback(move |_, clock| {
+            let frame_time = clock.frame_time();
+            let (refresh_us, predicted_us) = clock.refresh_info(frame_time);
+            let refresh_us = if refresh_us > 0 { refresh_us }
+                             else { display::DEFAULT_REFRESH_US };
+            let presentation_us = if predicted_us > 0 { predicted_us }
+                                  else { frame_time + refresh_us };
+
+            let target_us = match &mut *w.pending_tick.borrow_mut() {
+                Some(pending) => {
+                    pending.earliest_us.get_or_insert(presentation_us);
+                    pending.target_us
+                },
+                None => return glib::Continue(false)
+            };
+            if !display::frame_is_closest(presentation_us, refresh_us, target_us) {
+                return glib::Continue(true);
+            }
+
+            if let Some(pending) = w.pending_tick.take() {
+                let t_earliest = utc_now() + chrono::Duration::microseconds(
+                                        pending.earliest_us.unwrap_or(presentation_us)
+                                            - glib::monotonic_time());
+                w.show_tick(&pending.event, t_earliest);
+            }
+            glib::Continue(false)
+        });
+    }
+
+    /// Update GUI elements for a clock-tick, given the expected presentation time
+    /// of the first frame which could have shown it
+    fn show_tick(&self, event: &TickEvent, t_earliest: Timestamp) {
+        let (sig_txt, insig_txt) =
+            self.format.format_split(event.t_nominal, self.stddev_offset.get());
+        let hms_txt = match self.suppression {
+            DigitSuppression::Show =>
+                format!(r#"<span size="x-large">{}{}</span>"#, sig_txt, insig_txt),
+            DigitSuppression::Grey =>
+                format!(r#"<span size="x-large">{}<span alpha="40%">{}</span></span>"#,
+                        sig_txt, insig_txt),
+            DigitSuppression::Hide =>
+                format!(r#"<span size="x-large">{}</span>"#, sig_txt)
+        };
+        self.hms_label.set_markup(&hms_txt);
+
+        let phase_txt = format!(r#"<span size="small">{}</span>"#,
+                                display::phase_char(event));
+        self.phase_label.set_markup(&phase_txt);
+
+        // The required lead runs from the Ticker waking until the compositor could
+        // first present the tick, irrespective of how long the frame was then held back:
+        let latency = t_earliest - (event.t_transmit - event.wake_error);
+        let avg_latency = self.avg_latency.borrow_mut()
+                                          .add_duration(latency);
+        if display::is_first_in_second(event) {
+            if let Some(tkr) = self.tkr_channel.get() {
+                tkr.send(TickerMessage::Latency(avg_latency)).unwrap();
+            }
+
+            let latency_txt = format!("Display latency: {:.2}ms (lead {:.2}ms), {}",
+                    avg_latency.num_microseconds()
+                               .expect("Display latency should be finite") as f64 * 1e-3,
+                    event.lead.num_microseconds().unwrap_or(0) as f64 * 1e-3,
+                    self.jitter_txt.borrow());
+            self.latency_label.set_text(&latency_txt);
+
+            let diag_txt = format!(r#"<span font_family="monospace" size="small">{}\n{}</span>"#,
+                                   glib::markup_escape_text(&self.realtime_txt.borrow()),
+                                   glib::markup_escape_text(
+                                        self.tick_stats.borrow().report().trim_end()));
+            self.diag_label.set_markup(&diag_txt);
+        }
+    }
+
+    pub fn receive_offset(&self, event: OffsetEvent) {
+        self.stddev_offset.set(event.stddev_offset);
+
+        let offs_txt = format!("Offset: {:.1}ms ± {:.1}ms",
+                               event.avg_offset.num_microseconds()
+                                    .expect("Offset should be finite") as f64 * 1e-3,
+                               event.stddev_offset * 1e3);
+        self.avg_offs_label.set_text(&offs_txt);
+    }
+
+    /// Show fitted round-trip error models as a tooltip on the offset display
+    pub fn receive_calibration(&self, fits: &[RttFit]) {
+        let tip_txt = format_rtt_fits(fits);
+        self.avg_offs_label.set_tooltip_text(
+            if tip_txt.is_empty() { None } else { Some(&tip_txt) });
+    }
+}
+
+
+/// Prepare GTK window and subcomponents, with clock ticking thread
+fn on_activate(app: &gtk::Application, cfg: &ECConfig) {
+    let win = gtk::ApplicationWindow::new(app);
+    win.set_title("UTC Engineering Clock");
+    win.set_border_width(8);
+    win.set_position(gtk::WindowPosition::Center);
+    win.set_default_size(144, 48);
+    win.set_resizable(false);
+
+    eng_clock::logo::get_pixbuf().map(|pixbuf|
+        win.set_icon(Some(&pixbuf)) ).unwrap();
+
+    let widgets = Widgets::new(&win, &cfg.display);
+    let bus = EventBus::new();
+    bus.subscribe(EventFilter::all(), widgets.init_channel());
+
+    let mut ticker = Ticker::new(bus.clone(), &cfg.display, &cfg.ticker);
+    ticker.subscribe_offsets();
+    widgets.set_ticker_channel(ticker.get_sync());
+    widgets.track_visibility(&win);
+    let mut offest = OffsetEstimator::new(bus, &cfg.sync, &cfg.estimator);
+    thread::spawn(move || { ticker.run() });
+    thread::spawn(move || { offest.run() });
+
+    win.show_all();
+}
+
+
+/// Run the GTK application until its window is closed
+pub fn run_gui(cfg: ECConfig) {
+    let app =
+        gtk::Application::builder()
+            .application_id("uk.rwpenney.engclock")
+            .build();
+
+    app.connect_activate(move |app| on_activate(app, &cfg));
+    app.run_with_args::<&str>(&[]);
+}
+
+// (C)Copyright 2023, RW Penney
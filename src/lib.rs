@@ -20,18 +20,42 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>
  */
 
+pub mod audit;
+pub mod bus;
 pub mod config;
+#[cfg(unix)]
+pub mod daemon;
+pub mod diag;
+pub mod display;
+pub mod eventlog;
+#[cfg(feature = "gtk")]
 pub mod logo;
+pub mod netmon;
+pub mod ntp;
+pub mod query;
+pub mod record;
+pub mod report;
+pub mod rt;
+pub mod sim;
+pub mod survey;
 pub mod sync;
 pub mod stats;
+#[cfg(unix)]
+pub mod term;
 pub mod ticker;
+pub mod tune;
+#[cfg(unix)]
+pub mod workers;
 
 #[cfg(test)]
 mod testing;
 
+#[cfg(feature = "gtk")]
 use gtk::glib;
+use serde::{ Deserialize, Serialize };
 
 pub type Timestamp = chrono::DateTime<chrono::Utc>;
+#[cfg(feature = "gtk")]
 pub type UIsender = glib::Sender<UImessage>;
 pub type Ticker = ticker::Ticker;
 
@@ -39,7 +63,7 @@ pub const MILLIS_PER_DAY: f32 = 86400e3;
 
 
 /// Clock-ticking event
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TickEvent {
     /// The (corrected) time that should be displayed to the user
     pub t_nominal: Timestamp,
@@ -47,15 +71,28 @@ pub struct TickEvent {
     /// The (uncorrected) time at which this message was sent
     pub t_transmit: Timestamp,
 
-    /// The number of ticks since an arbitrary origin, typically the POSIX epoch
-    pub tick_id: i64
+    /// The number of tick periods since the POSIX epoch
+    pub tick_id: i64,
+
+    /// The time-interval between successive ticks, in microseconds
+    pub period_us: i64,
+
+    /// The interval by which this tick was sent early,
+    /// to compensate for the latency of updating the display
+    #[serde(with = "duration_secs")]
+    pub lead: chrono::Duration,
+
+    /// The delay between the intended and actual wakeup times of the Ticker
+    #[serde(with = "duration_secs")]
+    pub wake_error: chrono::Duration
 }
 
 
 /// Clock-offset update event
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct OffsetEvent {
     /// The latest best-fit correction to be added to the local clock
+    #[serde(with = "duration_secs")]
     pub avg_offset: chrono::Duration,
 
     /// The nominal error on the clock-offset, in seconds
@@ -63,10 +100,64 @@ pub struct OffsetEvent {
 }
 
 
-/// Messages that can be sent asynchronously to GTK main loop from other threads
+/// Outcome of a single NTP request
+#[derive(Clone, Debug, PartialEq)]
+pub struct PollEvent {
+    pub server: String,
+
+    /// The (uncorrected) local time at which the request completed
+    pub time: Timestamp,
+
+    pub result: Result<ntp::NtpExchange, ntp::NtpError>
+}
+
+
+/// Messages that can be sent asynchronously to the Ticker from other threads
+pub enum TickerMessage {
+    /// A new estimate of the local clock-offset
+    Offset(OffsetEvent),
+
+    /// The smoothed delay between sending a tick and it being displayed
+    Latency(chrono::Duration),
+
+    /// Whether the clock display is currently visible to the user
+    Visible(bool),
+
+    /// Request that the Ticker thread finishes
+    Stop
+}
+
+
+/// Messages that can be sent asynchronously to GTK main loop from other threads,
+/// and more generally published to subscribers of an EventBus
+#[derive(Clone, Debug)]
 pub enum UImessage {
     Tick(TickEvent),
-    Offset(OffsetEvent)
+    Offset(OffsetEvent),
+    Calibration(Vec<stats::RttFit>),
+    Realtime(rt::RealtimeStatus),
+    Poll(PollEvent)
+}
+
+
+/// Serialization of time-intervals as fractional seconds
+mod duration_secs {
+    use serde::{ Deserialize, Deserializer, Serializer };
+
+    pub fn serialize<S: Serializer>(dt: &chrono::Duration, ser: S) -> Result<S::Ok, S::Error> {
+        let secs = match dt.num_nanoseconds() {
+            Some(ns) => ns as f64 * 1e-9,
+            None => dt.num_milliseconds() as f64 * 1e-3
+        };
+
+        ser.serialize_f64(secs)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<chrono::Duration, D::Error> {
+        let secs = f64::deserialize(de)?;
+
+        Ok(chrono::Duration::nanoseconds((secs * 1e9).round() as i64))
+    }
 }
 
 
@@ -88,14 +179,14 @@ fn weak_rand() -> u32 {
 
     unsafe {
         COUNTER = (COUNTER * 0x5deece66d + 11) & ((1 << 48) - 1);
-        ((dt.as_nanos() * 0x56cae88f ^ COUNTER) % 4294967291) as u32
+        (((dt.as_nanos() * 0x56cae88f) ^ COUNTER) % 4294967291) as u32
     }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use super::weak_rand;
+    use super::*;
     use crate::testing::*;
 
     #[test]
@@ -116,6 +207,28 @@ mod tests {
             assert_close(vrnc, 1.0 / 12.0, 0.3 / (N as f64).sqrt());
         }
     }
+
+    #[test]
+    fn event_serialization() {
+        let tick = TickEvent {
+            t_nominal: mk_time(5, (250, 0, 0)),
+            t_transmit: mk_time(5, (249, 980, 0)),
+            tick_id: 123,
+            period_us: 250_000,
+            lead: chrono::Duration::milliseconds(20),
+            wake_error: chrono::Duration::microseconds(-125) };
+        let json = serde_json::to_string(&tick).unwrap();
+
+        assert!(json.contains(r#""lead":0.02,"wake_error":-0.000125"#));
+        assert_eq!(serde_json::from_str::<TickEvent>(&json).unwrap(), tick);
+
+        let offs = OffsetEvent { avg_offset: chrono::Duration::milliseconds(-3250),
+                                 stddev_offset: 0.25 };
+        let json = serde_json::to_string(&offs).unwrap();
+
+        assert_eq!(json, r#"{"avg_offset":-3.25,"stddev_offset":0.25}"#);
+        assert_eq!(serde_json::from_str::<OffsetEvent>(&json).unwrap(), offs);
+    }
 }
 
 // (C)Copyright 2023, RW Penney
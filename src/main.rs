@@ -15,164 +15,617 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>
  */
 
-use gtk::glib;
-use gtk::prelude::*;
-use std::{ cell::RefCell, rc::Rc, thread };
+use clap::{ Args, Parser, Subcommand, ValueEnum };
+use std::{ io::Write, path::PathBuf };
 
 use eng_clock::{
-    OffsetEvent, TickEvent, UImessage, UIsender, utc_now,
-    config::ECConfig,
-    stats::ExpoAvg,
-    sync::OffsetEstimator,
-    ticker::Ticker
+    audit,
+    config::{ ConfigOverrides, ConfigReadError, ECConfig },
+    eventlog::LogEntry,
+    record::ExchangeRecord,
+    report::SessionReport,
+    sim::Scenario,
+    stats::RttFit,
+    sync::{ OffsetTracker, SystemSource },
+    tune
 };
 
+#[cfg(feature = "gtk")]
+mod gui;
 
-/// Collection of GTK widgets that may need dynamic updates
-#[derive(Clone)]
-struct Widgets {
-    hms_label: gtk::Label,
-    phase_label: gtk::Label,
-    latency_label: gtk::Label,
-    avg_offs_label: gtk::Label,
 
-    avg_latency: Rc<RefCell<ExpoAvg>>
+/// Dynamically synchronized realtime clock display
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(flatten)]
+    settings: SettingsArgs,
+
+    /// The display used when no other mode is chosen
+    #[arg(long, value_enum, default_value_t = Frontend::default())]
+    frontend: Frontend,
+
+    /// Write a commented file of default settings to PATH, or to the per-user
+    /// configuration file if PATH is omitted, or to standard output if PATH is "-"
+    #[arg(long, value_name = "PATH")]
+    write_config: Option<Option<PathBuf>>,
+
+    #[command(flatten)]
+    verbosity: Verbosity,
+
+    #[command(subcommand)]
+    command: Option<Command>
+}
+
+
+/// Choice of configuration file, and settings which take precedence over it
+#[derive(Args)]
+struct SettingsArgs {
+    /// Read settings from this file, in place of the per-user configuration file
+    #[arg(long, short = 'c', global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Query this NTP server in place of those configured (may be repeated)
+    #[arg(long = "server", short = 's', global = true, value_name = "HOST")]
+    servers: Vec<String>,
+
+    /// The desired margin of error in the clock-offset, in seconds
+    #[arg(long, global = true, value_name = "SECONDS", value_parser = positive_seconds)]
+    target_precision: Option<f32>,
+
+    /// The minimum interval between NTP requests, in seconds
+    #[arg(long, global = true, value_name = "SECONDS", value_parser = positive_seconds)]
+    wakeup_interval: Option<f32>
+}
+
+impl SettingsArgs {
+    fn overrides(&self) -> ConfigOverrides {
+        ConfigOverrides {
+            ntp_servers: self.servers.clone(),
+            target_precision: self.target_precision,
+            wakeup_interval: self.wakeup_interval
+        }
+    }
+}
+
+
+/// Selection of the level of detail in diagnostic messages
+#[derive(Args)]
+struct Verbosity {
+    /// Report more detail (may be repeated)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Report only warnings and errors, or only errors if repeated
+    #[arg(short, long, global = true, action = clap::ArgAction::Count,
+          conflicts_with = "verbose")]
+    quiet: u8
+}
+
+impl Verbosity {
+    fn level(&self) -> log::LevelFilter {
+        const LEVELS: [log::LevelFilter; 6] = [
+            log::LevelFilter::Off, log::LevelFilter::Error, log::LevelFilter::Warn,
+            log::LevelFilter::Info, log::LevelFilter::Debug, log::LevelFilter::Trace ];
+        let idx = (3 + self.verbose as i32 - self.quiet as i32).clamp(0, 5);
+
+        LEVELS[idx as usize]
+    }
+}
+
+
+/// The means of showing the clock
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum Frontend {
+    /// A GTK window
+    #[cfg_attr(feature = "gtk", default)]
+    Gtk,
+
+    /// Large digits within the current terminal
+    #[cfg_attr(not(feature = "gtk"), default)]
+    Tui,
+
+    /// No display, with periodic status reports
+    Headless
+}
+
+
+/// The presentation of server-survey results
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum SurveyFormat {
+    /// Aligned columns, with times in milliseconds
+    #[default]
+    Table,
+
+    /// Comma-separated values, with times in seconds
+    Csv,
+
+    /// A JSON object, with times in seconds
+    Json
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Evaluate the offset estimator against a simulated network
+    Simulate {
+        /// TOML description of the simulated clock and servers
+        scenario: Option<PathBuf>
+    },
+
+    /// Feed a recording of NTP exchanges back through the offset estimator
+    Replay {
+        /// JSON-Lines file written via the "record_path" setting
+        recording: PathBuf
+    },
+
+    /// Rank estimator settings by their performance over a recording
+    Tune {
+        /// JSON-Lines file written via the "record_path" setting
+        recording: PathBuf,
+
+        /// The number of best-ranked settings to display
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+
+        /// Score penalty for each poll per hour, in nats
+        #[arg(long)]
+        poll_cost: Option<f64>,
+
+        /// Print the winning settings as a TOML configuration snippet
+        #[arg(long)]
+        toml: bool
+    },
+
+    /// Estimate the clock-offset once, succeeding if the target precision is reached
+    Query {
+        /// The longest time to spend polling NTP servers, in seconds
+        #[arg(long, default_value = "30", value_parser = positive_duration)]
+        timeout: std::time::Duration,
+
+        /// Print the result as a JSON object
+        #[arg(long)]
+        json: bool
+    },
+
+    /// Compare NTP servers by querying each of them repeatedly
+    Survey {
+        /// The servers to query, in place of those configured
+        #[arg(value_name = "HOST")]
+        servers: Vec<String>,
+
+        /// The number of queries to send to each server
+        #[arg(long, short = 'n', default_value_t = 8)]
+        count: u32,
+
+        /// The pause between successive rounds of queries, in seconds
+        #[arg(long, default_value = "1", value_parser = positive_duration)]
+        interval: std::time::Duration,
+
+        /// The presentation of the results
+        #[arg(long, value_enum, default_value_t)]
+        format: SurveyFormat
+    },
+
+    /// Write an HTML report, with charts, summarizing a session's event log
+    Report {
+        /// JSON-Lines files written via the "event_log_path" setting,
+        /// including any rotated logs
+        #[arg(required = true)]
+        logs: Vec<PathBuf>,
+
+        /// The HTML file to create, rather than writing to standard output
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+
+        /// The heading of the report
+        #[arg(long, default_value = "eng-clock session report")]
+        title: String
+    },
+
+    /// Check the integrity of an audit log, and summarize when the tolerance was exceeded
+    Verify {
+        /// JSON-Lines file written via the "audit_log_path" setting
+        log: PathBuf,
+
+        /// File containing the key with which records were signed,
+        /// in place of the configured "audit_key_path"
+        #[arg(long)]
+        key: Option<PathBuf>,
+
+        /// The largest permitted divergence from UTC, in seconds,
+        /// in place of that stored with each record
+        #[arg(long, value_parser = positive_seconds)]
+        tolerance: Option<f32>,
+
+        /// The sequence number of a record previously reported as final,
+        /// which the log must still include
+        #[arg(long, value_name = "SEQ")]
+        expect_seq: Option<u64>
+    },
+
+    /// Show the clock in large digits within the current terminal
+    #[cfg(unix)]
+    #[command(visible_alias = "tui")]
+    Terminal,
+
+    /// Run the clock without a display, e.g. as a systemd service
+    #[cfg(unix)]
+    Daemon {
+        /// The interval between status reports, in seconds
+        #[arg(long, default_value_t = 60)]
+        status_interval: u64
+    }
+}
+
+
+/// Describe each server's fitted margin of error versus round-trip time
+fn format_rtt_fits(fits: &[RttFit]) -> String {
+    fits.iter()
+        .map(|f| format!("{}: {:.3} × RTT + {:.2}ms (n={})",
+                         f.server, f.scale, f.floor * 1e3, f.samples))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+
+/// Interpret a command-line interval, which must be positive
+fn positive_seconds(arg: &str) -> Result<f32, String> {
+    match arg.parse::<f32>() {
+        Ok(x) if x > 0.0 && x.is_finite() => Ok(x),
+        Ok(_) => Err(String::from("must be a positive number of seconds")),
+        Err(e) => Err(e.to_string())
+    }
+}
+
+
+/// Interpret a command-line interval as a duration, which must be positive
+fn positive_duration(arg: &str) -> Result<std::time::Duration, String> {
+    let seconds = positive_seconds(arg)?;
+
+    std::time::Duration::try_from_secs_f32(seconds).map_err(|e| e.to_string())
+}
+
+
+/// Minimal logger, writing diagnostic messages to standard error
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        match record.level() {
+            log::Level::Info => eprintln!("{}", record.args()),
+            level =>           eprintln!("{}: {}", level, record.args())
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+
+/// Read the chosen configuration file, and apply any command-line overrides
+///
+/// An unreadable per-user configuration file is replaced by default settings,
+/// whereas an explicitly chosen file must be readable.
+fn read_config(settings: &SettingsArgs) -> Result<ECConfig, ConfigReadError> {
+    let mut cfg = match &settings.config {
+        Some(path) => ECConfig::from_path(path)?,
+        None => ECConfig::from_user_config().unwrap_or_else(|e| {
+            match e {
+                ConfigReadError::IoError(ref io)
+                        if io.kind() == std::io::ErrorKind::NotFound =>
+                    log::debug!("No configuration file found, using default settings"),
+                _ => log::warn!("Failed to read configuration file - {:?}", e)
+            }
+            ECConfig::default()
+        })
+    };
+    settings.overrides().apply(&mut cfg)?;
+
+    Ok(cfg)
+}
+
+
+/// Run a mode of operation with the chosen configuration, unless it is unreadable
+fn with_config<F>(settings: &SettingsArgs, run: F) -> std::process::ExitCode
+        where F: FnOnce(ECConfig) -> std::process::ExitCode {
+    match read_config(settings) {
+        Ok(cfg) => run(cfg),
+        Err(e) => {
+            eprintln!("Failed to read configuration file {:?} - {:?}",
+                      settings.config.clone().unwrap_or_default(), e);
+            std::process::ExitCode::FAILURE
+        }
+    }
 }
 
-impl Widgets {
-    pub fn new(root: &gtk::ApplicationWindow) -> Widgets {
-        let rtbox = gtk::Box::new(gtk::Orientation::Vertical, 3);
-        root.add(&rtbox);
 
-        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 2);
-        rtbox.pack_start(&hbox, false, false, 0);
+fn write_default_config(path: Option<PathBuf>) -> std::process::ExitCode {
+    let path = match path.map_or_else(ECConfig::user_config_path, Ok) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Failed to locate configuration directory - {:?}", e);
+            return std::process::ExitCode::FAILURE;
+        }
+    };
+    let doc = ECConfig::default_toml();
+
+    if path.as_os_str() == "-" {
+        print!("{}", doc);
+        return std::process::ExitCode::SUCCESS;
+    }
 
-        let hms_label = gtk::Label::new(None);
-        hms_label.set_halign(gtk::Align::Center);
-        hbox.pack_start(&hms_label, false, false, 2);
+    // Never overwrite existing settings:
+    let written = std::fs::OpenOptions::new().write(true).create_new(true).open(&path)
+                    .and_then(|mut file| file.write_all(doc.as_bytes()));
+    match written {
+        Ok(()) => {
+            println!("Wrote default configuration to {:?}", path);
+            std::process::ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("Failed to write configuration {:?} - {:?}", path, e);
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
 
-        let phase_label = gtk::Label::new(None);
-        phase_label.set_halign(gtk::Align::End);
-        hbox.pack_start(&phase_label, false, false, 6);
 
-        let vbox = gtk::Box::new(gtk::Orientation::Vertical, 2);
-        rtbox.pack_start(&vbox, false, false, 0);
+#[cfg(feature = "gtk")]
+fn run_gui(settings: &SettingsArgs) -> std::process::ExitCode {
+    with_config(settings, |cfg| {
+        gui::run_gui(cfg);
+        std::process::ExitCode::SUCCESS
+    })
+}
 
-        let avg_offs_label = gtk::Label::new(None);
-        avg_offs_label.set_halign(gtk::Align::Start);
-        vbox.pack_start(&avg_offs_label, false, false, 0);
+#[cfg(not(feature = "gtk"))]
+fn run_gui(_settings: &SettingsArgs) -> std::process::ExitCode {
+    eprintln!("This build of eng-clock lacks GTK support; try \"--help\" for other modes");
+    std::process::ExitCode::FAILURE
+}
 
-        let latency_label = gtk::Label::new(None);
-        latency_label.set_halign(gtk::Align::Start);
-        vbox.pack_start(&latency_label, false, false, 0);
 
-        Widgets {
-            hms_label,
-            phase_label,
-            avg_offs_label,
-            latency_label,
-            avg_latency: Rc::new(RefCell::new(ExpoAvg::new(0.1)))
+fn run_frontend(frontend: Frontend, settings: &SettingsArgs) -> std::process::ExitCode {
+    match frontend {
+        Frontend::Gtk => run_gui(settings),
+        #[cfg(unix)]
+        Frontend::Tui => with_config(settings, |cfg| eng_clock::term::run(&cfg)),
+        #[cfg(unix)]
+        Frontend::Headless => run_daemon(60, settings),
+        #[cfg(not(unix))]
+        _ => {
+            eprintln!("The {:?} frontend is not supported on this platform", frontend);
+            std::process::ExitCode::FAILURE
         }
     }
+}
 
-    /// Prepare UI-update communication channel and associated event handlers
-    pub fn init_channel(&self) -> UIsender {
-        let (sender, receiver) =
-            glib::MainContext::channel(glib::PRIORITY_HIGH);
-        let w = self.clone();
-
-        // Wire-up message handlers:
-        receiver.attach(None, move |msg| {
-            match msg {
-                UImessage::Tick(event) =>   w.receive_tick(event),
-                UImessage::Offset(event) => w.receive_offset(event)
-            };
-            glib::Continue(true)
-        });
-
-        sender
-    }
-
-    /// Update GUI elements after receiving clock-tick from Ticker
-    pub fn receive_tick(&self, event: TickEvent) {
-        const PHASE_CHARS: [char; 4] = [ '=', '.', ':', '\'' ];
-
-        let hms_txt = format!(r#"<span size="x-large">{}</span>"#,
-                              event.t_nominal.format("%H:%M:%S"));
-        self.hms_label.set_markup(&hms_txt);
-
-        let phase = (event.tick_id % 4) as usize;
-        let phase_txt = format!(r#"<span size="small">{}</span>"#,
-                                PHASE_CHARS[phase]);
-        self.phase_label.set_markup(&phase_txt);
-
-        let latency = utc_now() - event.t_transmit;
-        let avg_latency = self.avg_latency.borrow_mut()
-                                          .add_duration(latency);
-        // FIXME - screen-update latency is likely to be sub-millisecond, but might be worth including in ticker offset
-        if phase == 2 {
-            let latency_txt = format!("UI latency: {:.2}ms",
-                    avg_latency.num_microseconds()
-                               .expect("UI latency should be finite") as f64 * 1e-3);
-            self.latency_label.set_text(&latency_txt);
+
+fn run_simulation(path: Option<PathBuf>) -> std::process::ExitCode {
+    let scenario = match path {
+        Some(p) => match Scenario::from_path(&p) {
+            Ok(scen) => scen,
+            Err(e) => {
+                eprintln!("Failed to read scenario {:?} - {:?}", p, e);
+                return std::process::ExitCode::FAILURE;
+            }
+        },
+        None => Scenario::default()
+    };
+
+    println!("{}", eng_clock::sim::run_scenario(&scenario));
+
+    std::process::ExitCode::SUCCESS
+}
+
+
+fn run_replay(path: PathBuf, cfg: ECConfig) -> std::process::ExitCode {
+    let records = match ExchangeRecord::from_path(&path) {
+        Ok(recs) => recs,
+        Err(e) => {
+            eprintln!("Failed to read recording {:?} - {:?}", path, e);
+            return std::process::ExitCode::FAILURE;
         }
+    };
+
+    let (history, tracker) = eng_clock::record::replay(records, &cfg.sync,
+                                                       &cfg.estimator);
+    for (t, offs) in history {
+        println!("{}  offset: {:.3}ms ± {:.3}ms",
+                 t.format("%Y-%m-%d %H:%M:%S%.3f"),
+                 offs.avg_offset.num_microseconds().unwrap_or(0) as f64 * 1e-3,
+                 offs.stddev_offset * 1e3);
     }
 
-    pub fn receive_offset(&self, event: OffsetEvent) {
-        let offs_txt = format!("Offset: {:.1}ms ± {:.1}ms",
-                               event.avg_offset.num_microseconds()
-                                    .expect("Offset should be finit") as f64 * 1e-3,
-                               event.stddev_offset * 1e3);
-        self.avg_offs_label.set_text(&offs_txt);
+    let fits = format_rtt_fits(&tracker.rtt_fits());
+    if !fits.is_empty() {
+        println!("\nRound-trip error calibration:\n{}", fits);
     }
+
+    std::process::ExitCode::SUCCESS
 }
 
 
-fn read_config() -> ECConfig {
-    match ECConfig::from_user_config() {
-        Ok(cfg) => cfg,
+fn run_tuning(path: PathBuf, top: usize, poll_cost: Option<f64>,
+              toml: bool) -> std::process::ExitCode {
+    let records = match ExchangeRecord::from_path(&path) {
+        Ok(recs) => recs,
         Err(e) => {
-            println!("Failed to read configuration file - {:?}", e);
-            ECConfig::default()
+            eprintln!("Failed to read recording {:?} - {:?}", path, e);
+            return std::process::ExitCode::FAILURE;
+        }
+    };
+    let obs = tune::Observation::from_records(&records);
+    if obs.len() <= OffsetTracker::WARMUP_CYCLES as usize {
+        eprintln!("Recording contains too few successful observations");
+        return std::process::ExitCode::FAILURE;
+    }
+
+    let mut grid = tune::TuneGrid::default();
+    if let Some(cost) = poll_cost {
+        grid.poll_cost = cost;
+    }
+    let scores = tune::sweep(&obs, &grid);
+
+    print!("{}", tune::format_table(&scores[.. top.min(scores.len())]));
+    if toml {
+        println!();
+        print!("{}", tune::format_toml(&scores[0]));
+    }
+
+    std::process::ExitCode::SUCCESS
+}
+
+
+fn run_query(cfg: ECConfig, timeout: std::time::Duration,
+             json: bool) -> std::process::ExitCode {
+    let result = eng_clock::query::query(SystemSource::new(), &cfg.sync, &cfg.estimator,
+                                         timeout);
+
+    if json {
+        println!("{}", result.to_json());
+    } else {
+        print!("{}", result.to_text());
+    }
+
+    if result.precision_met { std::process::ExitCode::SUCCESS }
+    else { std::process::ExitCode::FAILURE }
+}
+
+
+fn run_survey(cfg: ECConfig, servers: Vec<String>, count: u32,
+              interval: std::time::Duration, format: SurveyFormat) -> std::process::ExitCode {
+    let servers = if servers.is_empty() { cfg.sync.ntp_servers } else { servers };
+    let report = eng_clock::survey::survey(SystemSource::new(), &servers, count.max(1),
+                                           interval);
+
+    match format {
+        SurveyFormat::Table =>  print!("{}", report.format_table()),
+        SurveyFormat::Csv =>    print!("{}", report.format_csv()),
+        SurveyFormat::Json =>   println!("{}", report.to_json())
+    }
+
+    if report.reachable() > 0 { std::process::ExitCode::SUCCESS }
+    else { std::process::ExitCode::FAILURE }
+}
+
+
+fn run_report(logs: Vec<PathBuf>, output: Option<PathBuf>,
+              title: String) -> std::process::ExitCode {
+    let mut entries = vec![];
+    for path in logs.iter() {
+        match LogEntry::from_path(path) {
+            Ok(mut ents) => entries.append(&mut ents),
+            Err(e) => {
+                eprintln!("Failed to read event log {:?} - {:?}", path, e);
+                return std::process::ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let html = match SessionReport::from_entries(entries) {
+        Some(report) => report.to_html(&title),
+        None => {
+            eprintln!("No events found in {:?}", logs);
+            return std::process::ExitCode::FAILURE;
+        }
+    };
+
+    match output {
+        Some(path) => match std::fs::write(&path, html) {
+            Ok(()) => std::process::ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("Failed to write report {:?} - {:?}", path, e);
+                std::process::ExitCode::FAILURE
+            }
+        },
+        None => {
+            print!("{}", html);
+            std::process::ExitCode::SUCCESS
         }
     }
 }
 
 
-/// Prepare GTK window and subcomponents, with clock ticking thread
-fn on_activate(app: &gtk::Application) {
-    let cfg = read_config();
-    let win = gtk::ApplicationWindow::new(app);
-    win.set_title("UTC Engineering Clock");
-    win.set_border_width(8);
-    win.set_position(gtk::WindowPosition::Center);
-    win.set_default_size(144, 48);
-    win.set_resizable(false);
+/// Check an audit log, exiting with status 1 if it is incomplete or altered,
+/// or 2 if intact but the tolerance was exceeded
+fn run_verify(cfg: ECConfig, path: PathBuf, key: Option<PathBuf>,
+              tolerance: Option<f32>, expect_seq: Option<u64>) -> std::process::ExitCode {
+    let key_path = key.or(cfg.sync.audit_key_path);
+    let key = match key_path.as_deref().map(audit::read_key).transpose() {
+        Ok(key) => key,
+        Err(e) => {
+            eprintln!("Failed to read audit key {:?} - {:?}", key_path, e);
+            return std::process::ExitCode::FAILURE;
+        }
+    };
 
-    eng_clock::logo::get_pixbuf().map(|pixbuf|
-        win.set_icon(Some(&pixbuf)) ).unwrap();
+    let mut result = match audit::verify_path(&path, key.as_deref(), tolerance.map(f64::from)) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Failed to read audit log {:?} - {:?}", path, e);
+            return std::process::ExitCode::FAILURE;
+        }
+    };
+    if let Some(seq) = expect_seq {
+        result.expect_seq(seq);
+    }
+    print!("{}", result.to_text());
+
+    if !result.intact() { std::process::ExitCode::FAILURE }
+    else if !result.exceedances.is_empty() { std::process::ExitCode::from(2) }
+    else { std::process::ExitCode::SUCCESS }
+}
 
-    let widgets = Widgets::new(&win);
-    let sender = widgets.init_channel();
 
-    let mut ticker = Ticker::new(sender.clone());
-    let mut offest = OffsetEstimator::new(ticker.get_sync(),
-                                          sender.clone(), &cfg.sync);
-    thread::spawn(move || { ticker.run() });
-    thread::spawn(move || { offest.run() });
+#[cfg(unix)]
+fn run_daemon(status_interval: u64, settings: &SettingsArgs) -> std::process::ExitCode {
+    let opts = eng_clock::daemon::DaemonOptions {
+        status_interval: std::time::Duration::from_secs(status_interval.max(1)) };
 
-    win.show_all();
+    eng_clock::daemon::run(|| read_config(settings), &opts)
 }
 
 
-fn main() {
-    let app =
-        gtk::Application::builder()
-            .application_id("uk.rwpenney.engclock")
-            .build();
+fn main() -> std::process::ExitCode {
+    let cli = Cli::parse();
+    let settings = &cli.settings;
+
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(cli.verbosity.level());
 
-    app.connect_activate(on_activate);
-    app.run();
+    if let Some(path) = cli.write_config {
+        return write_default_config(path);
+    }
+
+    match cli.command {
+        None => run_frontend(cli.frontend, settings),
+        Some(Command::Simulate { scenario }) => run_simulation(scenario),
+        Some(Command::Replay { recording }) =>
+            with_config(settings, |cfg| run_replay(recording, cfg)),
+        Some(Command::Tune { recording, top, poll_cost, toml }) =>
+            run_tuning(recording, top, poll_cost, toml),
+        Some(Command::Query { timeout, json }) =>
+            with_config(settings, |cfg| run_query(cfg, timeout, json)),
+        Some(Command::Report { logs, output, title }) => run_report(logs, output, title),
+        Some(Command::Verify { log, key, tolerance, expect_seq }) =>
+            with_config(settings, |cfg| run_verify(cfg, log, key, tolerance, expect_seq)),
+        Some(Command::Survey { servers, count, interval, format }) =>
+            with_config(settings, |cfg| run_survey(cfg, servers, count, interval, format)),
+        #[cfg(unix)]
+        Some(Command::Terminal) => run_frontend(Frontend::Tui, settings),
+        #[cfg(unix)]
+        Some(Command::Daemon { status_interval }) => run_daemon(status_interval, settings)
+    }
 }
 
 // (C)Copyright 2023, RW Penney
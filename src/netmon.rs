@@ -0,0 +1,280 @@
+/*
+ *  Detection of network configuration changes for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use std::{ sync::mpsc, time::{ Duration, Instant } };
+
+
+/// Source of notifications that network addresses or routes have changed
+pub trait NetworkEvents {
+    /// Pause for up to the given interval, returning early
+    /// with true if the network configuration changes
+    fn wait(&mut self, dt: Duration) -> bool;
+}
+
+
+/// Plain sleep, without any awareness of network changes
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoNetworkEvents;
+
+impl NetworkEvents for NoNetworkEvents {
+    fn wait(&mut self, dt: Duration) -> bool {
+        std::thread::sleep(dt);
+        false
+    }
+}
+
+
+/// Injected notifications, e.g. for testing, with each message denoting one change
+impl NetworkEvents for mpsc::Receiver<()> {
+    fn wait(&mut self, dt: Duration) -> bool {
+        match self.recv_timeout(dt) {
+            Ok(()) => {
+                while self.try_recv().is_ok() {}
+                true
+            },
+            Err(mpsc::RecvTimeoutError::Timeout) => false,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                std::thread::sleep(dt);
+                false
+            }
+        }
+    }
+}
+
+
+/// Wrapper which reports at most one network change within any holdoff period,
+/// deferring, rather than discarding, changes which arrive too soon
+pub struct RateLimited<E: NetworkEvents> {
+    inner: E,
+    holdoff: Duration,
+
+    /// When a change was last reported
+    last_report: Option<Instant>,
+
+    /// Whether a change arrived during the holdoff period
+    pending: bool
+}
+
+impl<E: NetworkEvents> RateLimited<E> {
+    pub fn new(inner: E, holdoff: Duration) -> RateLimited<E> {
+        RateLimited { inner, holdoff, last_report: None, pending: false }
+    }
+}
+
+impl<E: NetworkEvents> NetworkEvents for RateLimited<E> {
+    fn wait(&mut self, dt: Duration) -> bool {
+        let deadline = Instant::now() + dt;
+
+        loop {
+            let now = Instant::now();
+            let released = self.last_report.map_or(now, |t| t + self.holdoff);
+
+            if now < released {
+                // Absorb changes until the holdoff expires:
+                let until = released.min(deadline);
+                if self.inner.wait(until - now) {
+                    self.pending = true;
+                }
+                if Instant::now() >= deadline { return false; }
+            } else if std::mem::take(&mut self.pending) {
+                self.last_report = Some(now);
+                return true;
+            } else if now >= deadline {
+                return false;
+            } else if self.inner.wait(deadline - now) {
+                self.last_report = Some(Instant::now());
+                return true;
+            } else {
+                return false;
+            }
+        }
+    }
+}
+
+
+/// Subscription to rtnetlink multicast groups announcing changes
+/// to links, addresses and routes
+#[cfg(target_os = "linux")]
+pub struct NetlinkMonitor {
+    fd: libc::c_int
+}
+
+#[cfg(target_os = "linux")]
+impl NetlinkMonitor {
+    const GROUPS: u32 = (libc::RTMGRP_LINK | libc::RTMGRP_IPV4_IFADDR
+                         | libc::RTMGRP_IPV6_IFADDR | libc::RTMGRP_IPV4_ROUTE
+                         | libc::RTMGRP_IPV6_ROUTE) as u32;
+
+    pub fn new() -> std::io::Result<NetlinkMonitor> {
+        let fd = unsafe {
+            libc::socket(libc::AF_NETLINK, libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                         libc::NETLINK_ROUTE) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let monitor = NetlinkMonitor { fd };
+
+        let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups = NetlinkMonitor::GROUPS;
+
+        let rc = unsafe {
+            libc::bind(fd, &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                       std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t) };
+        if rc < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(monitor)
+    }
+
+    /// Discard all queued notifications, returning whether any were present
+    fn drain(&self) -> bool {
+        let mut buff = [0u8; 8192];
+        let mut any = false;
+
+        loop {
+            let len = unsafe {
+                libc::recv(self.fd, buff.as_mut_ptr() as *mut libc::c_void,
+                           buff.len(), libc::MSG_DONTWAIT) };
+            if len <= 0 {
+                // Treat a receive-buffer overflow as a change:
+                let overflow = len < 0 && std::io::Error::last_os_error()
+                                            .raw_os_error() == Some(libc::ENOBUFS);
+                return any || overflow;
+            }
+            any = true;
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl NetworkEvents for NetlinkMonitor {
+    fn wait(&mut self, dt: Duration) -> bool {
+        let deadline = std::time::Instant::now() + dt;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            let mut pfd = libc::pollfd { fd: self.fd, events: libc::POLLIN, revents: 0 };
+            let timeout_ms = remaining.as_millis().min(libc::c_int::MAX as u128)
+                                                  as libc::c_int;
+
+            let rc = unsafe { libc::poll(&mut pfd, 1, timeout_ms) };
+            if rc > 0 {
+                if self.drain() { return true; }
+            } else if rc == 0 {
+                return false;
+            } else if std::io::Error::last_os_error().kind()
+                        != std::io::ErrorKind::Interrupted {
+                std::thread::sleep(remaining);
+                return false;
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for NetlinkMonitor {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+
+/// The shortest interval between resynchronizations triggered by the live network
+#[cfg(target_os = "linux")]
+const RESYNC_HOLDOFF: Duration = Duration::from_secs(30);
+
+/// The most capable source of network-change events on this platform,
+/// rate-limited so that a flapping link cannot provoke continual re-polling
+pub fn system_events() -> Box<dyn NetworkEvents + Send> {
+    #[cfg(target_os = "linux")]
+    match NetlinkMonitor::new() {
+        Ok(monitor) => return Box::new(RateLimited::new(monitor, RESYNC_HOLDOFF)),
+        Err(e) => log::warn!("Failed to monitor network changes - {:?}", e)
+    }
+
+    Box::new(NoNetworkEvents)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{ SystemSource, TimeSource };
+
+    #[test]
+    fn injected_events() {
+        let (tx, mut rx) = mpsc::channel();
+        let dt = Duration::from_millis(20);
+
+        assert!(!rx.wait(dt));
+        tx.send(()).unwrap();
+        tx.send(()).unwrap();
+
+        let t0 = std::time::Instant::now();
+        assert!(rx.wait(Duration::from_secs(5)));
+        assert!(t0.elapsed() < Duration::from_secs(1));
+        assert!(!rx.wait(dt));
+
+        drop(tx);
+        assert!(!rx.wait(dt));
+    }
+
+    #[test]
+    fn interrupted_sleep() {
+        let (tx, rx) = mpsc::channel();
+        let mut src = SystemSource::with_events(Box::new(rx));
+
+        assert!(!src.take_network_change());
+        tx.send(()).unwrap();
+
+        let t0 = std::time::Instant::now();
+        src.sleep(Duration::from_secs(10));
+        assert!(t0.elapsed() < Duration::from_secs(2));
+        assert!(src.take_network_change());
+        assert!(!src.take_network_change());
+    }
+
+    #[test]
+    fn rate_limiting() {
+        let (tx, rx) = mpsc::channel();
+        let holdoff = Duration::from_millis(300);
+        let mut events = RateLimited::new(rx, holdoff);
+        let dt = Duration::from_millis(20);
+
+        tx.send(()).unwrap();
+        let t0 = Instant::now();
+        assert!(events.wait(Duration::from_secs(5)));
+
+        // A burst of further changes should be reported once, after the holdoff:
+        for _ in 0 .. 5 {
+            tx.send(()).unwrap();
+            assert!(!events.wait(dt));
+        }
+        assert!(events.wait(Duration::from_secs(5)));
+        assert!(t0.elapsed() >= holdoff);
+        assert!(!events.wait(dt));
+
+        // A quiet holdoff should not generate spurious reports:
+        std::thread::sleep(holdoff);
+        assert!(!events.wait(dt));
+        tx.send(()).unwrap();
+        assert!(events.wait(dt));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn netlink_timeout() {
+        // Subscribing needs no privileges, but may be blocked by sandboxing:
+        if let Ok(mut monitor) = NetlinkMonitor::new() {
+            let t0 = std::time::Instant::now();
+            monitor.wait(Duration::from_millis(30));
+            assert!(t0.elapsed() < Duration::from_secs(2));
+        }
+    }
+}
+
+// (C)Copyright 2026, RW Penney
@@ -0,0 +1,366 @@
+/*
+ *  Minimal SNTP client for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use serde::{ Deserialize, Serialize };
+use std::{
+    net::{ SocketAddr, ToSocketAddrs, UdpSocket },
+    time::{ Duration, Instant } };
+use crate::{ Timestamp, utc_now };
+
+
+/// The standard UDP port of an NTP server
+pub const NTP_PORT: u16 = 123;
+
+/// The number of seconds between the NTP epoch (1900) and the POSIX epoch (1970)
+const NTP_UNIX_DELTA: i64 = 2_208_988_800;
+
+/// The number of seconds in one NTP era
+const NTP_ERA: i64 = 1 << 32;
+
+
+/// Reasons why an NTP exchange may fail to produce a usable response
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NtpError {
+    /// The server hostname could not be resolved to a suitable address
+    AddressResolve,
+
+    /// A network error occurred while sending or receiving
+    Network,
+
+    /// No response arrived within the socket timeout
+    Timeout,
+
+    /// The response packet was malformed or did not match the request
+    InvalidResponse,
+
+    /// The server sent a kiss-o'-death packet, asking to be left alone
+    KissOfDeath
+}
+
+impl std::fmt::Display for NtpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let msg = match self {
+            NtpError::AddressResolve =>     "address resolution failed",
+            NtpError::Network =>            "network error",
+            NtpError::Timeout =>            "timed out",
+            NtpError::InvalidResponse =>    "invalid response",
+            NtpError::KissOfDeath =>        "kiss-o'-death"
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for NtpError {}
+
+
+/// A completed request/response exchange with an NTP server,
+/// following the timestamp notation of RFC-5905
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NtpExchange {
+    /// The hostname of the server that was queried
+    pub server: String,
+
+    /// The network address from which the response was received
+    pub address: SocketAddr,
+
+    /// The leap-second indicator (0 = none, 1 = +1s, 2 = -1s, 3 = unsynchronized)
+    pub leap: u8,
+
+    /// The stratum of the server's clock
+    pub stratum: u8,
+
+    /// The precision of the server's clock, as log2(seconds)
+    pub precision: i8,
+
+    /// The identifier of the server's own reference clock
+    pub ref_id: u32,
+
+    /// The (uncorrected) local time at which the request was sent (T1)
+    pub t_originate: Timestamp,
+
+    /// The server time at which the request arrived (T2)
+    pub t_receive: Timestamp,
+
+    /// The server time at which the response was sent (T3)
+    pub t_transmit: Timestamp,
+
+    /// The (uncorrected) local time at which the response arrived (T4)
+    pub t_destination: Timestamp
+}
+
+impl NtpExchange {
+    /// The correction to be added to the local clock, in seconds
+    pub fn offset(&self) -> f64 {
+        0.5 * (secs_between(self.t_originate, self.t_receive)
+                + secs_between(self.t_destination, self.t_transmit))
+    }
+
+    /// The round-trip network delay, excluding server processing time, in seconds
+    pub fn roundtrip(&self) -> f64 {
+        secs_between(self.t_originate, self.t_destination)
+            - secs_between(self.t_receive, self.t_transmit)
+    }
+
+    /// Human-readable form of the reference identifier,
+    /// which is an ASCII code for stratum-1 servers, or an IPv4 address otherwise
+    pub fn ref_id_string(&self) -> String {
+        let bytes = self.ref_id.to_be_bytes();
+
+        if self.stratum <= 1 {
+            bytes.iter().take_while(|&&b| b != 0)
+                 .map(|&b| if b.is_ascii_graphic() { b as char } else { '?' })
+                 .collect()
+        } else {
+            std::net::Ipv4Addr::from(bytes).to_string()
+        }
+    }
+}
+
+
+/// Elapsed time from t0 to t1, in seconds
+fn secs_between(t0: Timestamp, t1: Timestamp) -> f64 {
+    (t1 - t0).num_nanoseconds().map(|ns| ns as f64 * 1e-9)
+             .unwrap_or_else(|| (t1 - t0).num_milliseconds() as f64 * 1e-3)
+}
+
+
+/// Convert a UTC timestamp into 64-bit NTP fixed-point format
+pub fn to_ntp_time(t: Timestamp) -> u64 {
+    let secs = (t.timestamp() + NTP_UNIX_DELTA).rem_euclid(NTP_ERA) as u64;
+    let frac = ((t.timestamp_subsec_nanos() as u64) << 32) / 1_000_000_000;
+
+    (secs << 32) | frac
+}
+
+/// Convert a 64-bit NTP timestamp into UTC, assuming it lies
+/// within 68 years of 1968, or otherwise in the following era
+pub fn from_ntp_time(x: u64) -> Timestamp {
+    let mut secs = (x >> 32) as i64;
+    if secs & 0x8000_0000 == 0 {
+        secs += NTP_ERA;
+    }
+    let nanos = (((x & 0xffff_ffff) * 1_000_000_000 + (1 << 31)) >> 32) as u32;
+
+    Timestamp::from_timestamp(secs - NTP_UNIX_DELTA, 0).unwrap()
+        + chrono::Duration::nanoseconds(nanos as i64)
+}
+
+
+/// Simple SNTP client bound to a pair of IPv4 and IPv6 UDP sockets
+pub struct NtpClient {
+    skt_v4: UdpSocket,
+
+    /// The socket for IPv6 servers, unless IPv6 is unavailable on this host
+    skt_v6: Option<UdpSocket>,
+
+    /// The longest time waited for a response from any one server address
+    timeout: Duration
+}
+
+impl NtpClient {
+    /// The size of an NTP packet without extension fields
+    const PACKET_LEN: usize = 48;
+
+    pub fn new(timeout: Duration) -> std::io::Result<NtpClient> {
+        Ok(NtpClient {
+            skt_v4: UdpSocket::bind("0.0.0.0:0")?,
+            skt_v6: UdpSocket::bind("[::]:0").ok(),
+            timeout })
+    }
+
+    /// Perform a single request/response exchange with the given server,
+    /// trying each of its addresses in turn until one responds
+    pub fn query(&self, server: &str) -> Result<NtpExchange, NtpError> {
+        let addrs = (server, NTP_PORT).to_socket_addrs()
+                        .map_err(|_| NtpError::AddressResolve)?;
+        let mut err = NtpError::AddressResolve;
+
+        for addr in addrs {
+            let skt = match addr {
+                SocketAddr::V4(_) => &self.skt_v4,
+                SocketAddr::V6(_) => match &self.skt_v6 {
+                    Some(skt) => skt,
+                    None => continue
+                }
+            };
+
+            match self.exchange(skt, server, addr) {
+                Err(e @ (NtpError::Network | NtpError::Timeout)) => err = e,
+                result => return result
+            }
+        }
+
+        Err(err)
+    }
+
+    fn exchange(&self, skt: &UdpSocket, server: &str,
+                addr: SocketAddr) -> Result<NtpExchange, NtpError> {
+        let mut request = [0u8; NtpClient::PACKET_LEN];
+        request[0] = 0x23;      // LI=0, VN=4, Mode=3 (client)
+        let t_originate = utc_now();
+        let origin_stamp = to_ntp_time(t_originate);
+        request[40..48].copy_from_slice(&origin_stamp.to_be_bytes());
+
+        skt.send_to(&request, addr).map_err(|_| NtpError::Network)?;
+        let deadline = Instant::now() + self.timeout;
+
+        let mut response = [0u8; 1024];
+        loop {
+            // Stray packets must not extend the wait beyond the overall deadline:
+            let remaining = deadline.checked_duration_since(Instant::now())
+                                    .filter(|dt| !dt.is_zero())
+                                    .ok_or(NtpError::Timeout)?;
+            skt.set_read_timeout(Some(remaining)).map_err(|_| NtpError::Network)?;
+
+            let (len, from) = skt.recv_from(&mut response)
+                                 .map_err(|e| match e.kind() {
+                                     std::io::ErrorKind::WouldBlock |
+                                     std::io::ErrorKind::TimedOut =>
+                                         NtpError::Timeout,
+                                     _ => NtpError::Network })?;
+            let t_destination = utc_now();
+
+            // Discard stray packets, e.g. late replies to earlier requests:
+            if from != addr || len < NtpClient::PACKET_LEN
+                    || read_u64(&response, 24) != origin_stamp {
+                continue;
+            }
+
+            return parse_response(&response[..len], server, from,
+                                  t_originate, t_destination);
+        }
+    }
+}
+
+
+fn read_u64(buff: &[u8], pos: usize) -> u64 {
+    u64::from_be_bytes(buff[pos .. pos+8].try_into().unwrap())
+}
+
+
+/// Decode the header of a server response packet
+fn parse_response(buff: &[u8], server: &str, address: SocketAddr,
+                  t_originate: Timestamp, t_destination: Timestamp)
+        -> Result<NtpExchange, NtpError> {
+    let leap = buff[0] >> 6;
+    let mode = buff[0] & 0x07;
+    let stratum = buff[1];
+
+    if mode != 4 || read_u64(buff, 40) == 0 {
+        return Err(NtpError::InvalidResponse);
+    }
+    if stratum == 0 {
+        return Err(NtpError::KissOfDeath);
+    }
+
+    Ok(NtpExchange {
+        server: String::from(server),
+        address,
+        leap,
+        stratum,
+        precision: buff[3] as i8,
+        ref_id: u32::from_be_bytes(buff[12..16].try_into().unwrap()),
+        t_originate,
+        t_receive: from_ntp_time(read_u64(buff, 32)),
+        t_transmit: from_ntp_time(read_u64(buff, 40)),
+        t_destination
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    #[test]
+    fn ntp_time_roundtrip() {
+        for t in [ mk_time(0, (0, 0, 0)), mk_time(17, (250, 0, 0)),
+                   mk_time(86401, (999, 999, 999)),
+                   Timestamp::from_timestamp(2_200_000_000, 123_456_789).unwrap() ] {
+            let rt = from_ntp_time(to_ntp_time(t));
+            assert!((rt - t).num_nanoseconds().unwrap().abs() <= 1,
+                    "{:?} != {:?}", rt, t);
+        }
+
+        let unix0 = Timestamp::from_timestamp(0, 0).unwrap();
+        assert_eq!(to_ntp_time(unix0) >> 32, NTP_UNIX_DELTA as u64);
+    }
+
+    #[test]
+    fn exchange_arithmetic() {
+        let t0 = mk_time(100, (0, 0, 0));
+        let ms = |n: i64| chrono::Duration::milliseconds(n);
+        let ex = NtpExchange {
+            server: String::from("ntp.example"),
+            address: "192.0.2.1:123".parse().unwrap(),
+            leap: 0, stratum: 2, precision: -20, ref_id: 0xc0000202,
+            t_originate: t0,
+            t_receive: t0 + ms(530),
+            t_transmit: t0 + ms(531),
+            t_destination: t0 + ms(41)
+        };
+
+        assert_close(ex.offset(), 0.510, 1e-9);
+        assert_close(ex.roundtrip(), 0.040, 1e-9);
+        assert_eq!(ex.ref_id_string(), "192.0.2.2");
+
+        let gps = NtpExchange { stratum: 1,
+                                ref_id: u32::from_be_bytes(*b"GPS\0"), ..ex };
+        assert_eq!(gps.ref_id_string(), "GPS");
+    }
+
+    #[test]
+    fn response_parsing() {
+        let t1 = mk_time(5, (0, 0, 0));
+        let addr: SocketAddr = "192.0.2.7:123".parse().unwrap();
+        let mut pkt = [0u8; 48];
+        pkt[0] = 0x24;      // LI=0, VN=4, Mode=4 (server)
+        pkt[1] = 1;
+        pkt[3] = (-23i8) as u8;
+        pkt[12..16].copy_from_slice(b"PPS\0");
+        pkt[32..40].copy_from_slice(&to_ntp_time(t1).to_be_bytes());
+        pkt[40..48].copy_from_slice(&to_ntp_time(t1).to_be_bytes());
+
+        let ex = parse_response(&pkt, "s", addr, t1, t1).unwrap();
+        assert_eq!((ex.leap, ex.stratum, ex.precision), (0, 1, -23));
+        assert_eq!(ex.ref_id_string(), "PPS");
+
+        pkt[1] = 0;
+        assert_eq!(parse_response(&pkt, "s", addr, t1, t1),
+                   Err(NtpError::KissOfDeath));
+        pkt[0] = 0x23;
+        assert_eq!(parse_response(&pkt, "s", addr, t1, t1),
+                   Err(NtpError::InvalidResponse));
+    }
+
+    #[test]
+    fn stray_packets() {
+        let client = NtpClient::new(Duration::from_millis(300)).unwrap();
+        let port = client.skt_v4.local_addr().unwrap().port();
+        let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        // A steady stream of unsolicited packets should not prolong the wait:
+        let spammer = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let skt = UdpSocket::bind("127.0.0.1:0").unwrap();
+                while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+                    let _ = skt.send_to(&[ 0u8; 48 ], ( "127.0.0.1", port ));
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            })
+        };
+
+        let t0 = Instant::now();
+        assert_eq!(client.query("127.0.0.1"), Err(NtpError::Timeout));
+        assert!(t0.elapsed() < Duration::from_secs(2));
+
+        stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        spammer.join().unwrap();
+    }
+}
+
+// (C)Copyright 2026, RW Penney
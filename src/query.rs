@@ -0,0 +1,200 @@
+/*
+ *  One-shot estimation of the clock-offset for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use serde::Serialize;
+use std::time::Duration;
+use crate::{
+    Timestamp,
+    config::{ EstimatorConfig, SyncConfig },
+    ntp::{ NtpError, NtpExchange },
+    sync::{ OffsetTracker, TimeSource } };
+
+
+/// The outcome of polling until the clock-offset is known to a desired precision
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct QueryResult {
+    /// The best-fit correction to be added to the local clock, in seconds
+    pub offset: f64,
+
+    /// The standard deviation of the offset estimate, in seconds
+    pub stddev: f32,
+
+    /// The desired maximum uncertainty in the offset, in seconds
+    pub target_precision: f32,
+
+    /// Whether the uncertainty fell below the target precision
+    pub precision_met: bool,
+
+    /// The servers which responded, in order of their first response
+    pub servers: Vec<String>,
+
+    /// The number of successful NTP exchanges
+    pub samples: u32,
+
+    /// The number of failed NTP exchanges
+    pub failures: u32,
+
+    /// The local time spent polling, in seconds
+    pub elapsed: f64
+}
+
+impl QueryResult {
+    /// Render as a human-readable summary
+    pub fn to_text(&self) -> String {
+        format!("offset: {:+.3}ms ± {:.3}ms (target {:.3}ms {})\nservers: {}\nsamples: {} ({} failed) in {:.1}s\n",
+                self.offset * 1e3, self.stddev * 1e3, self.target_precision * 1e3,
+                if self.precision_met { "met" } else { "not met" },
+                if self.servers.is_empty() { String::from("none") }
+                else { self.servers.join(", ") },
+                self.samples, self.failures, self.elapsed)
+    }
+
+    /// Render as a single-line JSON object
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Query result should be serializable")
+    }
+}
+
+
+/// Wrapper around a time-source which tallies the outcomes of NTP exchanges
+struct CountingSource<S: TimeSource> {
+    inner: S,
+    servers: Vec<String>,
+    samples: u32,
+    failures: u32
+}
+
+impl<S: TimeSource> TimeSource for CountingSource<S> {
+    fn now(&self) -> Timestamp {
+        self.inner.now()
+    }
+
+    fn ntp_ping(&mut self, server: &str) -> Result<NtpExchange, NtpError> {
+        let result = self.inner.ntp_ping(server);
+
+        match &result {
+            Ok(exch) => {
+                self.samples += 1;
+                if !self.servers.contains(&exch.server) {
+                    self.servers.push(exch.server.clone());
+                }
+            },
+            Err(_) => self.failures += 1
+        }
+
+        result
+    }
+
+    fn sleep(&mut self, dt: Duration) {
+        self.inner.sleep(dt)
+    }
+
+    fn choose_server(&mut self, count: usize) -> usize {
+        self.inner.choose_server(count)
+    }
+
+    fn take_suspension(&mut self) -> Option<Duration> {
+        self.inner.take_suspension()
+    }
+
+    fn take_network_change(&mut self) -> bool {
+        self.inner.take_network_change()
+    }
+}
+
+
+/// Poll NTP servers, through at least the warmup cycles, until the uncertainty
+/// in the clock-offset falls below the target precision or the timeout expires
+pub fn query<S: TimeSource>(src: S, config: &SyncConfig, est_config: &EstimatorConfig,
+                            timeout: Duration) -> QueryResult {
+    let mut src = CountingSource { inner: src, servers: vec![],
+                                   samples: 0, failures: 0 };
+    let mut tracker = OffsetTracker::new(config, est_config);
+    let t_start = src.now();
+    let deadline = t_start + chrono::Duration::from_std(timeout)
+                                .unwrap_or(chrono::Duration::MAX);
+    let mut cycles = 0;
+
+    loop {
+        let (offs, pause) = tracker.step(&mut src);
+        cycles += 1;
+
+        let now = src.now();
+        let precision_met = src.samples > 0 && offs.stddev_offset < config.target_precision;
+
+        if (precision_met && cycles >= OffsetTracker::WARMUP_CYCLES) || now >= deadline {
+            return QueryResult {
+                offset: offs.avg_offset.num_microseconds().unwrap_or(0) as f64 * 1e-6,
+                stddev: offs.stddev_offset,
+                target_precision: config.target_precision,
+                precision_met,
+                servers: src.servers,
+                samples: src.samples,
+                failures: src.failures,
+                elapsed: (now - t_start).num_microseconds().unwrap_or(0) as f64 * 1e-6
+            };
+        }
+
+        src.sleep(pause.min((deadline - now).to_std().unwrap_or(Duration::ZERO)));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ sim::{ Scenario, SimNetwork }, testing::* };
+
+    fn scenario(loss: f64) -> Scenario {
+        Scenario::from_toml(&format!(r#"
+            duration = 600.0
+            seed = 5
+
+            [oscillator]
+            initial_error = 0.8
+            drift_ppm = 5.0
+
+            [[servers]]
+            name = "good.example"
+            loss = {}
+        "#, loss)).unwrap()
+    }
+
+    #[test]
+    fn precise_query() {
+        let scen = scenario(0.0);
+        let mut net = SimNetwork::new(&scen);
+        let result = query(&mut net, &scen.sync_config(), &scen.estimator,
+                           Duration::from_secs(60));
+
+        assert!(result.precision_met);
+        assert!(result.stddev < scen.target_precision);
+        assert_close(result.offset, -net.clock_error(), 0.01);
+        assert_eq!(result.samples, OffsetTracker::WARMUP_CYCLES);
+        assert_eq!(result.servers, [ "good.example" ]);
+        assert!(result.elapsed < 5.0);
+
+        let json = result.to_json();
+        assert!(json.contains(r#""precision_met":true"#));
+        assert!(json.contains(r#""servers":["good.example"]"#));
+        assert!(result.to_text().contains("target 30.000ms met"));
+    }
+
+    #[test]
+    fn unreachable_servers() {
+        let scen = scenario(1.0);
+        let result = query(SimNetwork::new(&scen), &scen.sync_config(), &scen.estimator,
+                           Duration::from_secs(20));
+
+        assert!(!result.precision_met);
+        assert_eq!(result.samples, 0);
+        assert!(result.failures > 0);
+        // The final polling cycle may overrun while waiting for responses:
+        assert!((20.0 .. 30.0).contains(&result.elapsed));
+        assert!(result.to_text().contains("servers: none"));
+    }
+}
+
+// (C)Copyright 2026, RW Penney
@@ -0,0 +1,381 @@
+/*
+ *  Recording and replay of NTP exchanges for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use serde::{ Deserialize, Serialize };
+use std::{
+    collections::VecDeque,
+    io::{ BufRead, Write },
+    path::Path };
+use crate::{
+    OffsetEvent, Timestamp,
+    config::{ EstimatorConfig, SyncConfig },
+    ntp::{ NtpError, NtpExchange },
+    sync::{ OffsetTracker, TimeSource } };
+
+
+/// A single NTP request, together with its response or failure
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeRecord {
+    /// The server hostname that was requested
+    pub server: String,
+
+    /// The (uncorrected) local time at which the request was initiated
+    pub start: Timestamp,
+
+    /// The (uncorrected) local time at which the request completed or was abandoned
+    pub end: Timestamp,
+
+    /// The full exchange timestamps, or the reason for failure
+    pub result: Result<NtpExchange, NtpError>,
+
+    /// The duration, in seconds, of any system suspension reported before this request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suspension: Option<f64>,
+
+    /// Whether a network change was reported before this request
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub network_change: bool
+}
+
+impl ExchangeRecord {
+    /// Read a sequence of records from a JSON-Lines document
+    pub fn read_all<R: BufRead>(rdr: R) -> std::io::Result<Vec<ExchangeRecord>> {
+        let mut records = vec![];
+
+        for line in rdr.lines() {
+            let line = line?;
+            if line.trim().is_empty() { continue; }
+
+            records.push(serde_json::from_str(&line)
+                            .map_err(|e| std::io::Error::new(
+                                            std::io::ErrorKind::InvalidData, e))?);
+        }
+
+        Ok(records)
+    }
+
+    /// Read a sequence of records from a JSON-Lines file
+    pub fn from_path(path: &Path) -> std::io::Result<Vec<ExchangeRecord>> {
+        let file = std::fs::File::open(path)?;
+        ExchangeRecord::read_all(std::io::BufReader::new(file))
+    }
+}
+
+
+/// Wrapper around a time-source which logs every NTP exchange as JSON-Lines
+///
+/// Any suspension or network change reported by the underlying time-source
+/// is attached to the record of the following exchange.
+pub struct RecordingSource<S: TimeSource, W: Write> {
+    inner: S,
+    out: W,
+
+    /// Suspensions reported since the previous exchange
+    suspension: Option<std::time::Duration>,
+
+    /// Whether a network change has been reported since the previous exchange
+    network_change: bool,
+
+    /// Whether a write failure has already been reported
+    failed: bool
+}
+
+impl<S: TimeSource, W: Write> RecordingSource<S, W> {
+    pub fn new(inner: S, out: W) -> RecordingSource<S, W> {
+        RecordingSource { inner, out, suspension: None, network_change: false,
+                          failed: false }
+    }
+
+    /// Recover the underlying time-source and output stream
+    pub fn into_inner(self) -> (S, W) {
+        ( self.inner, self.out )
+    }
+
+    fn write_record(&mut self, record: &ExchangeRecord) -> std::io::Result<()> {
+        serde_json::to_writer(&mut self.out, record)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()
+    }
+}
+
+impl<S: TimeSource, W: Write> TimeSource for RecordingSource<S, W> {
+    fn now(&self) -> Timestamp {
+        self.inner.now()
+    }
+
+    fn ntp_ping(&mut self, server: &str) -> Result<NtpExchange, NtpError> {
+        let start = self.inner.now();
+        let result = self.inner.ntp_ping(server);
+        let record = ExchangeRecord {
+            server: String::from(server),
+            start,
+            end: self.inner.now(),
+            result,
+            suspension: self.suspension.take().map(|d| d.as_secs_f64()),
+            network_change: std::mem::take(&mut self.network_change)
+        };
+
+        if let Err(e) = self.write_record(&record) {
+            if !self.failed {
+                log::warn!("Failed to record NTP exchange - {:?}", e);
+                self.failed = true;
+            }
+        }
+
+        record.result
+    }
+
+    fn sleep(&mut self, dt: std::time::Duration) {
+        self.inner.sleep(dt)
+    }
+
+    fn choose_server(&mut self, count: usize) -> usize {
+        self.inner.choose_server(count)
+    }
+
+    fn take_suspension(&mut self) -> Option<std::time::Duration> {
+        let gap = self.inner.take_suspension();
+        if let Some(gap) = gap {
+            self.suspension = Some(self.suspension.unwrap_or_default() + gap);
+        }
+
+        gap
+    }
+
+    fn take_network_change(&mut self) -> bool {
+        let changed = self.inner.take_network_change();
+        self.network_change |= changed;
+
+        changed
+    }
+}
+
+
+/// Time-source which plays back previously recorded NTP exchanges in virtual time
+///
+/// Servers are chosen in the recorded order, and each request consumes
+/// the next recorded exchange with the requested server, with the local clock
+/// jumping forward to the recorded completion time of that exchange.
+/// Suspensions and network changes are reported just before the
+/// exchange whose record they are attached to.
+pub struct ReplaySource {
+    records: VecDeque<ExchangeRecord>,
+
+    /// The distinct server names mentioned in the recording
+    servers: Vec<String>,
+
+    /// The current virtual time of the local clock
+    t: Timestamp
+}
+
+impl ReplaySource {
+    pub fn new(records: Vec<ExchangeRecord>) -> ReplaySource {
+        let t = records.first().map(|r| r.start)
+                       .unwrap_or(Timestamp::UNIX_EPOCH);
+        let mut servers: Vec<String> = vec![];
+
+        for rec in records.iter() {
+            if !servers.contains(&rec.server) {
+                servers.push(rec.server.clone());
+            }
+        }
+
+        ReplaySource { records: VecDeque::from(records), servers, t }
+    }
+
+    /// Check whether all recorded exchanges have been consumed
+    pub fn is_finished(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// The distinct server names mentioned in the recording,
+    /// in order of their first appearance
+    pub fn servers(&self) -> Vec<String> {
+        self.servers.clone()
+    }
+}
+
+impl TimeSource for ReplaySource {
+    fn now(&self) -> Timestamp {
+        self.t
+    }
+
+    fn ntp_ping(&mut self, server: &str) -> Result<NtpExchange, NtpError> {
+        let idx = self.records.iter().position(|r| r.server == server);
+
+        match idx.and_then(|i| self.records.remove(i)) {
+            Some(rec) => {
+                self.t = self.t.max(rec.end);
+                rec.result
+            },
+            None => Err(NtpError::Timeout)
+        }
+    }
+
+    fn sleep(&mut self, dt: std::time::Duration) {
+        self.t += chrono::Duration::from_std(dt).unwrap_or(chrono::Duration::zero());
+    }
+
+    fn choose_server(&mut self, count: usize) -> usize {
+        self.records.front()
+            .and_then(|r| self.servers.iter().position(|s| *s == r.server))
+            .filter(|&i| i < count)
+            .unwrap_or(0)
+    }
+
+    fn take_suspension(&mut self) -> Option<std::time::Duration> {
+        self.records.front_mut()
+            .and_then(|r| r.suspension.take())
+            .map(std::time::Duration::from_secs_f64)
+    }
+
+    fn take_network_change(&mut self) -> bool {
+        self.records.front_mut()
+            .is_some_and(|r| std::mem::take(&mut r.network_change))
+    }
+}
+
+
+/// Feed a recording through an offset-estimator, returning the sequence
+/// of offset estimates and the local times at which they were produced,
+/// together with the final state of the estimator
+pub fn replay(records: Vec<ExchangeRecord>, config: &SyncConfig,
+              est_config: &EstimatorConfig)
+        -> (Vec<(Timestamp, OffsetEvent)>, OffsetTracker) {
+    let mut src = ReplaySource::new(records);
+    let config = SyncConfig {
+        ntp_servers: src.servers(),
+        record_path: None,
+        event_log_path: None,
+        audit_log_path: None,
+        ..config.clone()
+    };
+    let mut tracker = OffsetTracker::new(&config, est_config);
+    let mut history = vec![];
+
+    while !src.is_finished() {
+        let (offs, pause) = tracker.step(&mut src);
+        history.push(( src.now(), offs ));
+        src.sleep(pause);
+    }
+
+    ( history, tracker )
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{ Scenario, SimNetwork, Suspension };
+
+    fn record_sim(scenario: &Scenario) -> (Vec<(Timestamp, OffsetEvent)>, Vec<u8>) {
+        let mut src = RecordingSource::new(SimNetwork::new(scenario), vec![]);
+        let mut tracker = OffsetTracker::new(&scenario.sync_config(),
+                                            &scenario.estimator);
+        let mut history = vec![];
+
+        while src.inner.elapsed() < scenario.duration {
+            let (offs, pause) = tracker.step(&mut src);
+            history.push(( src.now(), offs ));
+            src.sleep(pause);
+        }
+
+        ( history, src.into_inner().1 )
+    }
+
+    #[test]
+    fn record_format() {
+        let scen = Scenario { duration: 600.0, ..Scenario::default() };
+        let (_, log) = record_sim(&scen);
+
+        let records = ExchangeRecord::read_all(log.as_slice()).unwrap();
+        assert!(records.len() >= 3);
+        assert!(records.iter().all(|r| r.start <= r.end));
+
+        let first = std::str::from_utf8(&log).unwrap().lines().next().unwrap();
+        assert!(first.contains(r#""t_originate":"#));
+        assert!(first.contains(r#""address":"192.0.2."#));
+    }
+
+    #[test]
+    fn deterministic_replay() {
+        // Without random-walk, virtual sleeps are exactly reproducible:
+        let mut scen = Scenario { duration: 7200.0, ..Scenario::default() };
+        scen.oscillator.wander = 0.0;
+        let (live, log) = record_sim(&scen);
+
+        let records = ExchangeRecord::read_all(log.as_slice()).unwrap();
+        let n_failed = records.iter().filter(|r| r.result.is_err()).count();
+        let (replayed, _) = replay(records.clone(), &scen.sync_config(),
+                                   &scen.estimator);
+
+        assert!(n_failed < records.len());
+        assert!(replayed.len() > 3);
+        for ((t0, e0), (t1, e1)) in live.iter().zip(replayed.iter()) {
+            assert!((*t1 - *t0).num_microseconds().unwrap().abs() < 10);
+            assert_eq!(e0.avg_offset, e1.avg_offset);
+            assert_eq!(e0.stddev_offset, e1.stddev_offset);
+        }
+
+        assert_eq!(replay(records.clone(), &scen.sync_config(),
+                          &scen.estimator).0.len(),
+                   replayed.len());
+    }
+
+    #[test]
+    fn replayed_events() {
+        let mut scen = Scenario { duration: 7200.0, ..Scenario::default() };
+        scen.suspensions = vec![ Suspension { at: 2000.0, duration: 600.0,
+                                              clock_jump: 0.5 } ];
+        scen.network_changes = vec![ 5000.0 ];
+        let mut src = RecordingSource::new(SimNetwork::new(&scen), vec![]);
+        let mut tracker = OffsetTracker::new(&scen.sync_config(), &scen.estimator);
+
+        while src.inner.elapsed() < scen.duration {
+            let (_, pause) = tracker.step(&mut src);
+            src.sleep(pause);
+        }
+        assert_eq!(( tracker.resumptions(), tracker.network_changes() ), ( 1, 1 ));
+
+        let records = ExchangeRecord::read_all(src.into_inner().1.as_slice()).unwrap();
+        assert_eq!(records.iter().filter(|r| r.suspension.is_some()).count(), 1);
+        assert_eq!(records.iter().filter(|r| r.network_change).count(), 1);
+
+        let (_, replayed) = replay(records, &scen.sync_config(), &scen.estimator);
+        assert_eq!(( replayed.resumptions(), replayed.network_changes() ), ( 1, 1 ));
+    }
+
+    #[test]
+    fn server_selection() {
+        let record = |server: &str, result| {
+            ExchangeRecord { server: String::from(server), start: Timestamp::UNIX_EPOCH,
+                             end: Timestamp::UNIX_EPOCH, result,
+                             suspension: None, network_change: false } };
+        let mut src = ReplaySource::new(vec![
+                            record("a.example", Err(NtpError::Timeout)),
+                            record("b.example", Err(NtpError::Network)),
+                            record("a.example", Err(NtpError::KissOfDeath)) ]);
+
+        assert_eq!(src.servers(), [ "a.example", "b.example" ]);
+        assert_eq!(src.choose_server(2), 0);
+        assert_eq!(src.ntp_ping("b.example"), Err(NtpError::Network));
+        assert_eq!(src.ntp_ping("a.example"), Err(NtpError::Timeout));
+        assert_eq!(src.choose_server(2), 0);
+        assert_eq!(src.ntp_ping("b.example"), Err(NtpError::Timeout));
+        assert!(!src.is_finished());
+        assert_eq!(src.ntp_ping("a.example"), Err(NtpError::KissOfDeath));
+        assert!(src.is_finished());
+    }
+
+    #[test]
+    fn exhausted_replay() {
+        let mut src = ReplaySource::new(vec![]);
+
+        assert!(src.is_finished());
+        assert_eq!(src.ntp_ping("any.example"), Err(NtpError::Timeout));
+    }
+}
+
+// (C)Copyright 2026, RW Penney
@@ -0,0 +1,741 @@
+/*
+ *  Offline reports on clock-offset estimation sessions for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use std::fmt::Write;
+use crate::{
+    OffsetEvent, Timestamp,
+    eventlog::{ LogEntry, LogEvent, ResetReason },
+    ntp::NtpError,
+    stats::{ LinearFit, quantile } };
+
+
+/// The outcome of a single logged NTP request
+#[derive(Clone, Debug, PartialEq)]
+pub struct PollRecord {
+    pub time: Timestamp,
+    pub server: String,
+
+    /// The round-trip time and offset, in seconds, or the reason for failure
+    pub result: Result<(f64, f64), NtpError>
+}
+
+
+/// Round-trip times and reliability of a single server
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServerSummary {
+    pub server: String,
+
+    /// The number of requests sent
+    pub polls: u32,
+
+    /// The number of requests which failed
+    pub failures: u32,
+
+    /// The round-trip times of successful requests, in ascending order, in seconds
+    pub rtts: Vec<f64>
+}
+
+impl ServerSummary {
+    /// The given quantile of the round-trip times, in seconds
+    pub fn rtt_quantile(&self, q: f64) -> Option<f64> {
+        quantile(&self.rtts, q)
+    }
+}
+
+
+/// A period during which every NTP request failed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outage {
+    /// The time of the first failed request
+    pub start: Timestamp,
+
+    /// The time of the next successful request, or of the last failure
+    pub end: Timestamp,
+
+    /// The number of failed requests
+    pub failures: u32
+}
+
+
+/// Linear trend in the measured clock-offset
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Drift {
+    /// The rate at which the local clock gains on the reference, in parts-per-million
+    pub ppm: f64,
+
+    /// The standard error of the drift rate, in parts-per-million
+    pub stddev_ppm: Option<f64>,
+
+    /// The number of offset measurements contributing to the fit
+    pub samples: u32
+}
+
+
+/// History of a session, reconstructed from its event log
+#[derive(Clone, Debug)]
+pub struct SessionReport {
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub polls: Vec<PollRecord>,
+    pub offsets: Vec<(Timestamp, OffsetEvent)>,
+    pub resets: Vec<(Timestamp, ResetReason)>,
+
+    /// Statistics for each server, in alphabetical order
+    pub servers: Vec<ServerSummary>,
+
+    pub outages: Vec<Outage>,
+
+    /// The fitted drift of the local clock, if at least two offsets were measured
+    pub drift: Option<Drift>
+}
+
+impl SessionReport {
+    /// Analyze a (possibly unordered) sequence of log entries,
+    /// unless there are no entries at all
+    pub fn from_entries(mut entries: Vec<LogEntry>) -> Option<SessionReport> {
+        entries.sort_by_key(|e| e.time);
+        let start = entries.first()?.time;
+        let end = entries.last()?.time;
+
+        let mut polls = vec![];
+        let mut offsets = vec![];
+        let mut resets = vec![];
+        for entry in entries {
+            match entry.event {
+                LogEvent::Poll { server, rtt, offset, error, .. } => {
+                    let result = match ( rtt, offset, error ) {
+                        ( Some(rtt), Some(offs), None ) => Ok(( rtt, offs )),
+                        ( _, _, Some(e) ) => Err(e),
+                        _ => Err(NtpError::InvalidResponse)
+                    };
+                    polls.push(PollRecord { time: entry.time, server, result });
+                },
+                LogEvent::Offset(offs) => offsets.push(( entry.time, offs )),
+                LogEvent::Reset { reason } => resets.push(( entry.time, reason ))
+            }
+        }
+
+        Some(SessionReport {
+            servers: SessionReport::summarize_servers(&polls),
+            outages: SessionReport::find_outages(&polls),
+            drift: SessionReport::fit_drift(&polls, start),
+            start, end, polls, offsets, resets })
+    }
+
+    fn summarize_servers(polls: &[PollRecord]) -> Vec<ServerSummary> {
+        let mut servers: Vec<ServerSummary> = vec![];
+
+        for poll in polls.iter() {
+            let idx = match servers.iter().position(|s| s.server == poll.server) {
+                Some(idx) => idx,
+                None => {
+                    servers.push(ServerSummary { server: poll.server.clone(),
+                                                 ..ServerSummary::default() });
+                    servers.len() - 1
+                }
+            };
+            let srv = &mut servers[idx];
+
+            srv.polls += 1;
+            match poll.result {
+                Ok(( rtt, _ )) => srv.rtts.push(rtt),
+                Err(_) => srv.failures += 1
+            }
+        }
+
+        for srv in servers.iter_mut() {
+            srv.rtts.sort_by(f64::total_cmp);
+        }
+        servers.sort_by(|a, b| a.server.cmp(&b.server));
+
+        servers
+    }
+
+    fn find_outages(polls: &[PollRecord]) -> Vec<Outage> {
+        let mut outages = vec![];
+        let mut current: Option<Outage> = None;
+
+        for poll in polls.iter() {
+            match ( poll.result.is_ok(), &mut current ) {
+                ( true, Some(_) ) => {
+                    let mut outage = current.take().unwrap();
+                    outage.end = poll.time;
+                    outages.push(outage);
+                },
+                ( false, Some(outage) ) => {
+                    outage.end = poll.time;
+                    outage.failures += 1;
+                },
+                ( false, None ) =>
+                    current = Some(Outage { start: poll.time, end: poll.time, failures: 1 }),
+                ( true, None ) => {}
+            }
+        }
+        outages.extend(current);
+
+        outages
+    }
+
+    fn fit_drift(polls: &[PollRecord], start: Timestamp) -> Option<Drift> {
+        let mut lin = LinearFit::default();
+
+        for poll in polls.iter() {
+            if let Ok(( _, offs )) = poll.result {
+                lin.add_sample(seconds_between(start, poll.time), offs);
+            }
+        }
+
+        // A positive trend in the correction implies that the local clock is losing time:
+        let (slope, _) = lin.fit()?;
+
+        Some(Drift { ppm: -slope * 1e6,
+                     stddev_ppm: lin.slope_stddev().map(|s| s * 1e6),
+                     samples: lin.samples() })
+    }
+
+    /// The number of failed NTP requests
+    pub fn failures(&self) -> usize {
+        self.polls.iter().filter(|p| p.result.is_err()).count()
+    }
+
+    /// Render as a self-contained HTML document, with embedded SVG charts
+    pub fn to_html(&self, title: &str) -> String {
+        let mut html = String::new();
+        let title = escape(title);
+
+        let _ = write!(html, r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; color: #222; }}
+table {{ border-collapse: collapse; margin-bottom: 1.5em; }}
+th, td {{ border: 1px solid #bbb; padding: 0.2em 0.6em; text-align: right; }}
+th:first-child, td:first-child {{ text-align: left; }}
+svg {{ max-width: 100%; height: auto; display: block; margin-bottom: 1.5em; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+"#, title = title);
+
+        self.write_summary(&mut html);
+        self.write_servers(&mut html);
+        self.write_outages(&mut html);
+
+        let axis = TimeAxis::new(self.start, self.end);
+        html += "<h2>Clock offset</h2>\n";
+        html += &self.offset_chart(&axis);
+        html += "<h2>Offset uncertainty</h2>\n";
+        html += &self.stddev_chart(&axis);
+        html += "<h2>Poll times</h2>\n";
+        html += &self.poll_chart(&axis);
+        html += "<h2>Round-trip times</h2>\n";
+        html += &self.rtt_chart();
+        html += "</body>\n</html>\n";
+
+        html
+    }
+
+    fn write_summary(&self, html: &mut String) {
+        let final_offset = self.offsets.last().map_or(String::from("unknown"), |(_, offs)|
+            format!("{:+.3}ms ± {:.3}ms",
+                    offs.avg_offset.num_microseconds().unwrap_or(0) as f64 * 1e-3,
+                    offs.stddev_offset * 1e3));
+        let drift = self.drift.map_or(String::from("unknown"), |d| match d.stddev_ppm {
+            Some(err) => format!("{:+.2}ppm ± {:.2}ppm", d.ppm, err),
+            None => format!("{:+.2}ppm", d.ppm)
+        });
+
+        let _ = write!(html, "<table>\n\
+                              <tr><td>Start</td><td>{}</td></tr>\n\
+                              <tr><td>End</td><td>{}</td></tr>\n\
+                              <tr><td>Duration</td><td>{:.1}h</td></tr>\n\
+                              <tr><td>NTP requests</td><td>{} ({} failed)</td></tr>\n\
+                              <tr><td>Offset estimates</td><td>{}</td></tr>\n\
+                              <tr><td>Final offset</td><td>{}</td></tr>\n\
+                              <tr><td>Local clock drift (gain)</td><td>{}</td></tr>\n\
+                              <tr><td>Estimator resets</td><td>{}</td></tr>\n\
+                              </table>\n",
+                       format_time(self.start), format_time(self.end),
+                       seconds_between(self.start, self.end) / 3600.0,
+                       self.polls.len(), self.failures(), self.offsets.len(),
+                       final_offset, drift, self.resets.len());
+    }
+
+    fn write_servers(&self, html: &mut String) {
+        let ms = |x: Option<f64>| x.map_or(String::from("-"), |v| format!("{:.3}", v * 1e3));
+
+        *html += "<h2>Servers</h2>\n<table>\n<tr><th>Server</th><th>Requests</th><th>Failures</th><th>Min RTT (ms)</th><th>Median RTT (ms)</th><th>Max RTT (ms)</th></tr>\n";
+        for srv in self.servers.iter() {
+            let _ = writeln!(html, "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                             escape(&srv.server), srv.polls, srv.failures,
+                             ms(srv.rtt_quantile(0.0)), ms(srv.rtt_quantile(0.5)),
+                             ms(srv.rtt_quantile(1.0)));
+        }
+        *html += "</table>\n";
+    }
+
+    fn write_outages(&self, html: &mut String) {
+        *html += "<h2>Outages</h2>\n";
+        if self.outages.is_empty() {
+            *html += "<p>No failed requests.</p>\n";
+            return;
+        }
+
+        *html += "<table>\n<tr><th>Start</th><th>End</th><th>Duration (s)</th><th>Failed requests</th></tr>\n";
+        for outage in self.outages.iter() {
+            let _ = writeln!(html, "<tr><td>{}</td><td>{}</td><td>{:.1}</td><td>{}</td></tr>",
+                             format_time(outage.start), format_time(outage.end),
+                             seconds_between(outage.start, outage.end), outage.failures);
+        }
+        *html += "</table>\n";
+    }
+
+    /// Mark outages and resets on a time-series chart
+    fn annotate(&self, chart: &mut Chart, axis: &TimeAxis) {
+        for outage in self.outages.iter() {
+            chart.band(axis.value(outage.start), axis.value(outage.end), "#f6d3d3");
+        }
+        for (t, _) in self.resets.iter() {
+            chart.vline(axis.value(*t), "#e08a00");
+        }
+    }
+
+    fn offset_chart(&self, axis: &TimeAxis) -> String {
+        let measured: Vec<(f64, f64)> =
+            self.polls.iter()
+                .filter_map(|p| p.result.ok().map(|(_, offs)| ( axis.value(p.time), offs * 1e3 )))
+                .collect();
+        let estimated: Vec<(f64, f64)> =
+            self.offsets.iter()
+                .map(|(t, offs)| ( axis.value(*t),
+                                   offs.avg_offset.num_microseconds().unwrap_or(0) as f64 * 1e-3 ))
+                .collect();
+
+        let mut chart = Chart::new(axis.scale(),
+                                   Scale::new(measured.iter().chain(estimated.iter())
+                                                      .map(|p| p.1), false),
+                                   &axis.label(), "offset (ms)");
+        self.annotate(&mut chart, axis);
+        chart.dots(&measured, "#999999");
+        chart.polyline(&estimated, "#1f5fa8");
+
+        chart.to_svg()
+    }
+
+    fn stddev_chart(&self, axis: &TimeAxis) -> String {
+        let stddevs: Vec<(f64, f64)> =
+            self.offsets.iter()
+                .map(|(t, offs)| ( axis.value(*t), offs.stddev_offset as f64 * 1e3 ))
+                .collect();
+
+        let mut chart = Chart::new(axis.scale(), Scale::new(stddevs.iter().map(|p| p.1), true),
+                                   &axis.label(), "standard deviation (ms)");
+        self.annotate(&mut chart, axis);
+        chart.polyline(&stddevs, "#1f5fa8");
+
+        chart.to_svg()
+    }
+
+    fn poll_chart(&self, axis: &TimeAxis) -> String {
+        let names: Vec<String> = self.servers.iter().map(|s| s.server.clone()).collect();
+        let row = |server: &str| names.iter().position(|n| n == server).unwrap_or(0) as f64;
+        let (good, bad): (Vec<&PollRecord>, Vec<&PollRecord>) =
+            self.polls.iter().partition(|p| p.result.is_ok());
+
+        let mut chart = Chart::new(axis.scale(), Scale::categories(names.len()),
+                                   &axis.label(), "");
+        chart.categories = names.clone();
+        self.annotate(&mut chart, axis);
+        chart.dots(&good.iter().map(|p| ( axis.value(p.time), row(&p.server) ))
+                               .collect::<Vec<_>>(), "#2e8b3a");
+        chart.dots(&bad.iter().map(|p| ( axis.value(p.time), row(&p.server) ))
+                              .collect::<Vec<_>>(), "#c0392b");
+
+        chart.to_svg()
+    }
+
+    fn rtt_chart(&self) -> String {
+        let mut chart = Chart::new(Scale::new(self.servers.iter()
+                                                  .flat_map(|s| s.rtts.iter().map(|r| r * 1e3)),
+                                              false),
+                                   Scale::categories(self.servers.len()),
+                                   "round-trip time (ms)", "");
+        chart.categories = self.servers.iter().map(|s| s.server.clone()).collect();
+
+        for (idx, srv) in self.servers.iter().enumerate() {
+            let q: Vec<f64> = [ 0.0, 0.25, 0.5, 0.75, 1.0 ].iter()
+                                .filter_map(|&q| srv.rtt_quantile(q).map(|r| r * 1e3))
+                                .collect();
+            if q.len() == 5 {
+                chart.boxplot(idx as f64, [ q[0], q[1], q[2], q[3], q[4] ], "#1f5fa8");
+            }
+        }
+
+        chart.to_svg()
+    }
+}
+
+
+/// The interval between two times, in seconds
+fn seconds_between(t0: Timestamp, t1: Timestamp) -> f64 {
+    (t1 - t0).num_microseconds().unwrap_or(i64::MAX) as f64 * 1e-6
+}
+
+
+fn format_time(t: Timestamp) -> String {
+    t.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+
+/// Replace characters which are special within HTML text or attributes
+fn escape(txt: &str) -> String {
+    txt.replace('&', "&amp;").replace('<', "&lt;")
+       .replace('>', "&gt;").replace('"', "&quot;")
+}
+
+
+/// Conversion of timestamps into elapsed time, in units suited to the session length
+struct TimeAxis {
+    start: Timestamp,
+    end: Timestamp,
+
+    /// The number of seconds per unit, and the name of that unit
+    unit: (f64, &'static str)
+}
+
+impl TimeAxis {
+    fn new(start: Timestamp, end: Timestamp) -> TimeAxis {
+        let span = seconds_between(start, end);
+        let unit = if span >= 7200.0 { ( 3600.0, "h" ) }
+                   else if span >= 120.0 { ( 60.0, "min" ) }
+                   else { ( 1.0, "s" ) };
+
+        TimeAxis { start, end, unit }
+    }
+
+    fn value(&self, t: Timestamp) -> f64 {
+        seconds_between(self.start, t) / self.unit.0
+    }
+
+    fn scale(&self) -> Scale {
+        Scale::new([ 0.0, self.value(self.end) ].into_iter(), false)
+    }
+
+    fn label(&self) -> String {
+        format!("time since {} ({})", format_time(self.start), self.unit.1)
+    }
+}
+
+
+/// Mapping of data values onto one axis of a chart
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Scale {
+    /// The range of (log10 of) values spanned by the axis
+    lo: f64,
+    hi: f64,
+
+    log: bool
+}
+
+impl Scale {
+    /// An axis spanning the given values, extended to whole decades if logarithmic
+    fn new<I: Iterator<Item=f64>>(values: I, log: bool) -> Scale {
+        let (mut lo, mut hi) = ( f64::INFINITY, f64::NEG_INFINITY );
+
+        for v in values.filter(|v| v.is_finite() && (!log || *v > 0.0)) {
+            let v = if log { v.log10() } else { v };
+            lo = lo.min(v);
+            hi = hi.max(v);
+        }
+
+        if lo > hi {
+            ( lo, hi ) = ( 0.0, 1.0 );
+        }
+        if log {
+            ( lo, hi ) = ( lo.floor(), hi.ceil().max(lo.floor() + 1.0) );
+        } else if hi - lo < 1e-9 {
+            ( lo, hi ) = ( lo - 0.5, hi + 0.5 );
+        }
+
+        Scale { lo, hi, log }
+    }
+
+    /// An axis with one row for each of a number of named categories
+    fn categories(n: usize) -> Scale {
+        Scale { lo: -0.5, hi: n.max(1) as f64 - 0.5, log: false }
+    }
+
+    /// The fractional position of a value along the axis
+    fn frac(&self, v: f64) -> f64 {
+        let v = if self.log { v.max(f64::MIN_POSITIVE).log10() } else { v };
+
+        (v - self.lo) / (self.hi - self.lo)
+    }
+
+    /// Positions and labels of tick-marks, at round numbers
+    fn ticks(&self) -> Vec<(f64, String)> {
+        if self.log {
+            return (self.lo as i32 ..= self.hi as i32)
+                        .map(|e| ( 10f64.powi(e),
+                                   format!("{:.*}", (-e).max(0) as usize, 10f64.powi(e)) ))
+                        .collect();
+        }
+
+        let raw = (self.hi - self.lo) / 5.0;
+        let mag = 10f64.powf(raw.log10().floor());
+        let step = mag * match raw / mag {
+            r if r <= 1.0 => 1.0,
+            r if r <= 2.0 => 2.0,
+            r if r <= 5.0 => 5.0,
+            _ => 10.0
+        };
+        let decimals = (-step.log10().floor()).max(0.0) as usize;
+        let first = (self.lo / step).ceil() as i64;
+        let last = (self.hi / step + 1e-9).floor() as i64;
+
+        (first ..= last).map(|i| {
+                let v = i as f64 * step;
+                ( v, format!("{:.*}", decimals, v) )
+            }).collect()
+    }
+}
+
+
+/// A two-dimensional plot, rendered as SVG
+struct Chart {
+    x: Scale,
+    y: Scale,
+    x_label: String,
+    y_label: String,
+
+    /// Names of rows, in place of numerical y-values, if not empty
+    categories: Vec<String>,
+
+    /// The SVG elements representing the data
+    body: String
+}
+
+impl Chart {
+    const WIDTH: f64 = 760.0;
+    const HEIGHT: f64 = 280.0;
+    const MARGIN_TOP: f64 = 12.0;
+    const MARGIN_RIGHT: f64 = 16.0;
+    const MARGIN_BOTTOM: f64 = 42.0;
+
+    fn new(x: Scale, y: Scale, x_label: &str, y_label: &str) -> Chart {
+        Chart { x, y, x_label: String::from(x_label), y_label: String::from(y_label),
+                categories: vec![], body: String::new() }
+    }
+
+    fn margin_left(&self) -> f64 {
+        let longest = self.categories.iter().map(|c| c.chars().count()).max().unwrap_or(0);
+
+        (12.0 + 6.5 * longest as f64).clamp(64.0, 240.0)
+    }
+
+    fn px(&self, x: f64) -> f64 {
+        let left = self.margin_left();
+
+        left + self.x.frac(x).clamp(0.0, 1.0) * (Chart::WIDTH - left - Chart::MARGIN_RIGHT)
+    }
+
+    fn py(&self, y: f64) -> f64 {
+        Chart::HEIGHT - Chart::MARGIN_BOTTOM
+            - self.y.frac(y).clamp(0.0, 1.0)
+                * (Chart::HEIGHT - Chart::MARGIN_TOP - Chart::MARGIN_BOTTOM)
+    }
+
+    /// Shade the full height of the plot between two x-values
+    fn band(&mut self, x0: f64, x1: f64, colour: &str) {
+        let (left, right) = ( self.px(x0), self.px(x1) );
+
+        let _ = writeln!(self.body, r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"/>"#,
+                         left, Chart::MARGIN_TOP, (right - left).max(1.0),
+                         Chart::HEIGHT - Chart::MARGIN_TOP - Chart::MARGIN_BOTTOM, colour);
+    }
+
+    fn vline(&mut self, x: f64, colour: &str) {
+        let px = self.px(x);
+
+        let _ = writeln!(self.body, r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-dasharray="4 3"/>"#,
+                         px, Chart::MARGIN_TOP, px, Chart::HEIGHT - Chart::MARGIN_BOTTOM, colour);
+    }
+
+    fn polyline(&mut self, points: &[(f64, f64)], colour: &str) {
+        if points.is_empty() { return; }
+
+        let coords: Vec<String> = points.iter()
+                                        .map(|&(x, y)| format!("{:.1},{:.1}", self.px(x), self.py(y)))
+                                        .collect();
+        let _ = writeln!(self.body, r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="1.5"/>"#,
+                         coords.join(" "), colour);
+    }
+
+    fn dots(&mut self, points: &[(f64, f64)], colour: &str) {
+        for &(x, y) in points.iter() {
+            let _ = writeln!(self.body, r#"<circle cx="{:.1}" cy="{:.1}" r="2" fill="{}"/>"#,
+                             self.px(x), self.py(y), colour);
+        }
+    }
+
+    /// Draw a horizontal box-and-whisker summary from the minimum, quartiles and maximum
+    fn boxplot(&mut self, y: f64, q: [f64; 5], colour: &str) {
+        let (top, mid, bottom) = ( self.py(y + 0.3), self.py(y), self.py(y - 0.3) );
+
+        let _ = writeln!(self.body, r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}"/>"#,
+                         self.px(q[0]), mid, self.px(q[4]), mid, colour);
+        let _ = writeln!(self.body, r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="white" stroke="{}"/>"#,
+                         self.px(q[1]), top, (self.px(q[3]) - self.px(q[1])).max(1.0),
+                         bottom - top, colour);
+        let _ = writeln!(self.body, r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-width="2"/>"#,
+                         self.px(q[2]), top, self.px(q[2]), bottom, colour);
+    }
+
+    fn to_svg(&self) -> String {
+        let left = self.margin_left();
+        let bottom = Chart::HEIGHT - Chart::MARGIN_BOTTOM;
+        let mut svg = format!(r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-size="11">
+<rect x="{left}" y="{top}" width="{pw}" height="{ph}" fill="none" stroke="#444"/>
+"##,
+                              w = Chart::WIDTH, h = Chart::HEIGHT, left = left,
+                              top = Chart::MARGIN_TOP,
+                              pw = Chart::WIDTH - left - Chart::MARGIN_RIGHT,
+                              ph = bottom - Chart::MARGIN_TOP);
+
+        for (v, label) in self.x.ticks() {
+            let px = self.px(v);
+            let _ = writeln!(svg, r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#ddd"/><text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"##,
+                             px, Chart::MARGIN_TOP, px, bottom, px, bottom + 14.0, label);
+        }
+
+        let y_ticks: Vec<(f64, String)> = if self.categories.is_empty() {
+            self.y.ticks()
+        } else {
+            self.categories.iter().enumerate()
+                .map(|(i, name)| ( i as f64, escape(name) ))
+                .collect()
+        };
+        for (v, label) in y_ticks {
+            let py = self.py(v);
+            let _ = writeln!(svg, r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#ddd"/><text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"##,
+                             left, py, Chart::WIDTH - Chart::MARGIN_RIGHT, py,
+                             left - 4.0, py + 4.0, label);
+        }
+
+        svg += &self.body;
+        let _ = writeln!(svg, r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+                         (left + Chart::WIDTH - Chart::MARGIN_RIGHT) / 2.0,
+                         Chart::HEIGHT - 8.0, escape(&self.x_label));
+        if !self.y_label.is_empty() {
+            let ymid = (Chart::MARGIN_TOP + bottom) / 2.0;
+            let _ = writeln!(svg, r#"<text x="14" y="{:.1}" text-anchor="middle" transform="rotate(-90 14 {:.1})">{}</text>"#,
+                             ymid, ymid, escape(&self.y_label));
+        }
+        svg += "</svg>\n";
+
+        svg
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    /// A two-hour session with a 5ppm fast clock, and a ten-minute outage
+    fn session() -> Vec<LogEntry> {
+        let mut entries = vec![];
+
+        for i in 0 .. 120 {
+            let time = mk_time(60 * i, (0, 0, 0));
+            let server = if i % 2 == 0 { "a.example" } else { "<b>.example" };
+            let event = if (40 .. 50).contains(&i) {
+                LogEvent::Poll { server: String::from(server), address: None,
+                                 rtt: None, offset: None, error: Some(NtpError::Timeout) }
+            } else {
+                LogEvent::Poll { server: String::from(server),
+                                 address: Some(String::from("192.0.2.1:123")),
+                                 rtt: Some(0.02 + 0.001 * (i % 5) as f64),
+                                 offset: Some(0.1 - 5e-6 * 60.0 * i as f64),
+                                 error: None }
+            };
+            entries.push(LogEntry { time, event });
+            entries.push(LogEntry {
+                time: time + chrono::Duration::milliseconds(1),
+                event: LogEvent::Offset(OffsetEvent {
+                    avg_offset: chrono::Duration::microseconds(100_000 - 300 * i as i64),
+                    stddev_offset: 0.01 }) });
+        }
+        entries.push(LogEntry { time: mk_time(3000, (0, 0, 0)),
+                                event: LogEvent::Reset { reason: ResetReason::NetworkChange } });
+        entries.reverse();
+
+        entries
+    }
+
+    #[test]
+    fn scales() {
+        let lin = Scale::new([ 0.1, 0.93 ].into_iter(), false);
+        let labels: Vec<String> = lin.ticks().into_iter().map(|t| t.1).collect();
+        assert_eq!(labels, [ "0.2", "0.4", "0.6", "0.8" ]);
+        assert_close(lin.frac(0.1), 0.0, 1e-12);
+
+        let log = Scale::new([ 0.003, 25.0, -1.0 ].into_iter(), true);
+        assert_eq!(( log.lo, log.hi ), ( -3.0, 2.0 ));
+        let labels: Vec<String> = log.ticks().into_iter().map(|t| t.1).collect();
+        assert_eq!(labels, [ "0.001", "0.01", "0.1", "1", "10", "100" ]);
+        assert_close(log.frac(0.1), 0.4, 1e-12);
+
+        let flat = Scale::new([ 2.0, 2.0 ].into_iter(), false);
+        assert_eq!(( flat.lo, flat.hi ), ( 1.5, 2.5 ));
+    }
+
+    #[test]
+    fn session_analysis() {
+        assert!(SessionReport::from_entries(vec![]).is_none());
+
+        let report = SessionReport::from_entries(session()).unwrap();
+
+        assert_eq!(report.start, mk_time(0, (0, 0, 0)));
+        assert_eq!(( report.polls.len(), report.failures() ), ( 120, 10 ));
+        assert_eq!(report.offsets.len(), 120);
+        assert_eq!(report.resets, [ ( mk_time(3000, (0, 0, 0)), ResetReason::NetworkChange ) ]);
+
+        assert_eq!(report.servers.len(), 2);
+        let srv = &report.servers[1];
+        assert_eq!(( srv.server.as_str(), srv.polls, srv.failures ), ( "a.example", 60, 5 ));
+        assert_close(srv.rtt_quantile(0.0).unwrap(), 0.02, 1e-9);
+        assert_close(srv.rtt_quantile(1.0).unwrap(), 0.024, 1e-9);
+
+        assert_eq!(report.outages, [ Outage { start: mk_time(2400, (0, 0, 0)),
+                                              end: mk_time(3000, (0, 0, 0)),
+                                              failures: 10 } ]);
+
+        let drift = report.drift.unwrap();
+        assert_close(drift.ppm, 5.0, 1e-6);
+        assert_eq!(drift.samples, 110);
+    }
+
+    #[test]
+    fn html_document() {
+        let html = SessionReport::from_entries(session()).unwrap()
+                        .to_html("Bench <7>");
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Bench &lt;7&gt;</title>"));
+        assert_eq!(html.matches("<svg ").count(), 4);
+        assert_eq!(html.matches("</svg>").count(), 4);
+        assert!(html.contains("&lt;b&gt;.example"));
+        assert!(!html.contains("<b>"));
+        assert!(html.contains("+5.00ppm"));
+        assert!(html.contains("time since 1991-07-10 00:00:00 UTC (min)"));
+    }
+}
+
+// (C)Copyright 2026, RW Penney
@@ -0,0 +1,236 @@
+/*
+ *  Real-time scheduling controls for the eng-clock ticker thread
+ *  RW Penney, October 2026
+ */
+
+use serde::Deserialize;
+
+
+/// The operating-system scheduling policy requested for the Ticker thread
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SchedPolicy {
+    /// Ordinary time-shared scheduling
+    #[default]
+    Other,
+
+    /// Real-time first-in, first-out scheduling
+    Fifo,
+
+    /// Real-time round-robin scheduling
+    RR
+}
+
+impl std::fmt::Display for SchedPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            SchedPolicy::Other =>   "SCHED_OTHER",
+            SchedPolicy::Fifo =>    "SCHED_FIFO",
+            SchedPolicy::RR =>      "SCHED_RR"
+        };
+        f.write_str(name)
+    }
+}
+
+
+/// Real-time settings to be applied to the calling thread
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RealtimeRequest {
+    pub policy: SchedPolicy,
+
+    /// The static priority within a real-time policy (typically 1-99)
+    pub priority: i32,
+
+    /// The CPUs on which the thread may run, or empty to leave unrestricted
+    pub cpu_affinity: Vec<usize>,
+
+    /// Whether all process memory should be locked into RAM
+    pub lock_memory: bool
+}
+
+
+/// The scheduling settings actually achieved, with any reasons for falling short
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RealtimeStatus {
+    pub policy: SchedPolicy,
+    pub priority: i32,
+
+    /// Whether the requested CPU affinity was applied
+    pub affinity: bool,
+
+    /// Whether process memory is locked into RAM
+    pub memory_locked: bool,
+
+    /// Descriptions of any requested settings which could not be applied
+    pub warnings: Vec<String>
+}
+
+impl std::fmt::Display for RealtimeStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "scheduling: {}", self.policy)?;
+        if self.policy != SchedPolicy::Other {
+            write!(f, " (priority {})", self.priority)?;
+        }
+        if self.affinity {
+            f.write_str(", pinned")?;
+        }
+        if self.memory_locked {
+            f.write_str(", memory locked")?;
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Apply real-time settings to the calling thread, degrading to
+/// ordinary scheduling where privileges or platform support are lacking
+pub fn apply(request: &RealtimeRequest) -> RealtimeStatus {
+    let mut status = RealtimeStatus::default();
+
+    if request.policy != SchedPolicy::Other {
+        if let Err(e) = imp::set_policy(request.policy, request.priority) {
+            status.warnings.push(format!("Failed to select {} priority {} - {}",
+                                         request.policy, request.priority, e));
+        }
+    }
+
+    if !request.cpu_affinity.is_empty() {
+        match imp::set_affinity(&request.cpu_affinity) {
+            Ok(()) => status.affinity = true,
+            Err(e) => status.warnings.push(
+                        format!("Failed to set CPU affinity {:?} - {}",
+                                request.cpu_affinity, e))
+        }
+    }
+
+    if request.lock_memory {
+        match imp::lock_memory() {
+            Ok(()) => status.memory_locked = true,
+            Err(e) => status.warnings.push(format!("Failed to lock memory - {}", e))
+        }
+    }
+
+    ( status.policy, status.priority ) = imp::get_policy();
+
+    status
+}
+
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::SchedPolicy;
+    use std::io::Error;
+
+    pub fn set_policy(policy: SchedPolicy, priority: i32) -> Result<(), Error> {
+        let native = match policy {
+            SchedPolicy::Other =>   libc::SCHED_OTHER,
+            SchedPolicy::Fifo =>    libc::SCHED_FIFO,
+            SchedPolicy::RR =>      libc::SCHED_RR
+        };
+        let param = libc::sched_param { sched_priority: priority };
+
+        let rc = unsafe {
+            libc::pthread_setschedparam(libc::pthread_self(), native, &param) };
+        if rc == 0 { Ok(()) } else { Err(Error::from_raw_os_error(rc)) }
+    }
+
+    pub fn get_policy() -> (SchedPolicy, i32) {
+        let mut native = 0;
+        let mut param = libc::sched_param { sched_priority: 0 };
+
+        let rc = unsafe {
+            libc::pthread_getschedparam(libc::pthread_self(), &mut native, &mut param) };
+        if rc != 0 {
+            return ( SchedPolicy::Other, 0 );
+        }
+
+        match native {
+            libc::SCHED_FIFO => ( SchedPolicy::Fifo, param.sched_priority ),
+            libc::SCHED_RR =>   ( SchedPolicy::RR, param.sched_priority ),
+            _ =>                ( SchedPolicy::Other, 0 )
+        }
+    }
+
+    pub fn set_affinity(cpus: &[usize]) -> Result<(), Error> {
+        let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+
+        for &cpu in cpus {
+            if cpu >= libc::CPU_SETSIZE as usize {
+                return Err(Error::from_raw_os_error(libc::EINVAL));
+            }
+            unsafe { libc::CPU_SET(cpu, &mut set) };
+        }
+
+        // A thread-ID of zero denotes the calling thread:
+        let rc = unsafe {
+            libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) };
+        if rc == 0 { Ok(()) } else { Err(Error::last_os_error()) }
+    }
+
+    pub fn lock_memory() -> Result<(), Error> {
+        let rc = unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) };
+        if rc == 0 { Ok(()) } else { Err(Error::last_os_error()) }
+    }
+}
+
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use super::SchedPolicy;
+    use std::io::{ Error, ErrorKind };
+
+    fn unsupported() -> Error {
+        Error::new(ErrorKind::Unsupported, "not supported on this platform")
+    }
+
+    pub fn set_policy(_policy: SchedPolicy, _priority: i32) -> Result<(), Error> {
+        Err(unsupported())
+    }
+
+    pub fn get_policy() -> (SchedPolicy, i32) {
+        ( SchedPolicy::Other, 0 )
+    }
+
+    pub fn set_affinity(_cpus: &[usize]) -> Result<(), Error> {
+        Err(unsupported())
+    }
+
+    pub fn lock_memory() -> Result<(), Error> {
+        Err(unsupported())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordinary_request() {
+        let status = std::thread::spawn(|| apply(&RealtimeRequest::default()))
+                        .join().unwrap();
+
+        assert!(status.warnings.is_empty());
+        assert!(!status.affinity && !status.memory_locked);
+        assert_eq!(status.to_string(), "scheduling: SCHED_OTHER");
+    }
+
+    #[test]
+    fn degraded_request() {
+        // Whether or not privileges are available, the reported
+        // policy should either match the request, or be explained:
+        let request = RealtimeRequest { policy: SchedPolicy::Fifo, priority: 5,
+                                        cpu_affinity: vec![ 1 << 20 ],
+                                        lock_memory: false };
+        let status = std::thread::spawn(move || apply(&request))
+                        .join().unwrap();
+
+        assert!(!status.affinity);
+        assert!(status.warnings.iter().any(|w| w.contains("CPU affinity")));
+        assert!(status.policy == SchedPolicy::Fifo
+                || status.warnings.iter().any(|w| w.contains("SCHED_FIFO")));
+    }
+}
+
+// (C)Copyright 2026, RW Penney
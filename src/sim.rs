@@ -0,0 +1,748 @@
+/*
+ *  Offline network simulator for evaluating eng-clock synchronization
+ *  RW Penney, October 2026
+ */
+
+use serde::Deserialize;
+use std::{ net::SocketAddr, path::Path };
+use crate::{
+    Timestamp,
+    config::{ ConfigReadError, EstimatorConfig, SyncConfig },
+    ntp::{ NtpError, NtpExchange },
+    sync::{ OffsetTracker, TimeSource } };
+
+
+/// Deterministic pseudo-random number generator (SplitMix64)
+#[derive(Clone, Debug)]
+pub struct SimRng {
+    state: u64
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> SimRng {
+        SimRng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed sample within the interval (0, 1)
+    pub fn uniform(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+
+    /// Normally distributed sample with zero mean and unit variance
+    pub fn gaussian(&mut self) -> f64 {
+        let (u, v) = (self.uniform(), self.uniform());
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+
+    /// Exponentially distributed sample with given mean
+    pub fn exponential(&mut self, mean: f64) -> f64 {
+        -mean * self.uniform().ln()
+    }
+}
+
+
+/// Model of the local oscillator driving the system clock
+#[derive(Clone, Debug, Deserialize)]
+pub struct OscillatorModel {
+    /// The initial error of the local clock relative to true time, in seconds
+    #[serde(default)]
+    pub initial_error: f64,
+
+    /// The constant fractional frequency error, in parts-per-million
+    #[serde(default)]
+    pub drift_ppm: f64,
+
+    /// The random-walk growth of the clock error, in seconds per square-root day
+    #[serde(default)]
+    pub wander: f64
+}
+
+
+/// Model of a single NTP server and the network path to it
+#[derive(Clone, Debug, Deserialize)]
+pub struct ServerModel {
+    /// The hostname by which the server is known to the estimator
+    pub name: String,
+
+    /// The minimum one-way network delay, in seconds
+    #[serde(default = "ServerModel::default_delay")]
+    pub delay: f64,
+
+    /// The mean of the exponentially distributed queueing delay
+    /// added to each direction, in seconds
+    #[serde(default = "ServerModel::default_jitter")]
+    pub jitter: f64,
+
+    /// The excess of the outbound over the return delay, in seconds
+    #[serde(default)]
+    pub asymmetry: f64,
+
+    /// The error of the server's clock relative to true time, in seconds,
+    /// which is non-zero for a falseticker
+    #[serde(default)]
+    pub bias: f64,
+
+    /// The probability of any single request being lost
+    #[serde(default)]
+    pub loss: f64,
+
+    /// Intervals of simulated time, in seconds, during which the server is unreachable
+    #[serde(default)]
+    pub outages: Vec<(f64, f64)>,
+
+    /// The precision of the server's clock, as log2(seconds)
+    #[serde(default = "ServerModel::default_precision")]
+    pub precision: i8,
+
+    /// The stratum of the server's clock
+    #[serde(default = "ServerModel::default_stratum")]
+    pub stratum: u8
+}
+
+impl ServerModel {
+    fn default_delay() -> f64 { 0.01 }
+    fn default_jitter() -> f64 { 0.002 }
+    fn default_precision() -> i8 { -20 }
+    fn default_stratum() -> u8 { 2 }
+
+    fn is_reachable(&self, t: f64) -> bool {
+        !self.outages.iter().any(|&(t0, t1)| t0 <= t && t < t1)
+    }
+}
+
+
+/// An abrupt change in the local clock, such as caused by an external time-setting
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct ClockStep {
+    /// The simulated time at which the step occurs, in seconds
+    pub at: f64,
+
+    /// The amount added to the local clock, in seconds
+    pub amount: f64
+}
+
+
+/// A period during which the host is suspended, after which
+/// the local clock is restored with an additional error
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Suspension {
+    /// The simulated time at which the suspension begins, in seconds
+    pub at: f64,
+
+    /// The duration of the suspension, in seconds
+    pub duration: f64,
+
+    /// The amount added to the local clock on resumption, in seconds
+    #[serde(default)]
+    pub clock_jump: f64
+}
+
+
+/// Complete description of a simulated network and local clock
+#[derive(Clone, Debug, Deserialize)]
+pub struct Scenario {
+    /// The total simulated duration, in seconds
+    pub duration: f64,
+
+    /// The seed for all pseudo-random processes within the simulation
+    #[serde(default)]
+    pub seed: u64,
+
+    pub oscillator: OscillatorModel,
+
+    pub servers: Vec<ServerModel>,
+
+    #[serde(default)]
+    pub steps: Vec<ClockStep>,
+
+    #[serde(default)]
+    pub suspensions: Vec<Suspension>,
+
+    /// The simulated times, in seconds, at which the network configuration changes
+    #[serde(default)]
+    pub network_changes: Vec<f64>,
+
+    /// The desired margin of error in the estimated clock-offset, in seconds
+    #[serde(default = "Scenario::default_tgt_precision")]
+    pub target_precision: f32,
+
+    /// The minimum time interval between NTP requests, in seconds
+    #[serde(default = "Scenario::default_wakeup_itvl")]
+    pub wakeup_interval: f32,
+
+    /// Hyperparameters of the offset estimator under evaluation
+    #[serde(default)]
+    pub estimator: EstimatorConfig
+}
+
+impl Scenario {
+    /// A day-long mixture of good, asymmetric, unreliable and false servers
+    const DEFAULT_TOML: &'static str = r#"
+        duration = 86400.0
+        seed = 17
+
+        [oscillator]
+        initial_error = 1.5
+        drift_ppm = 12.0
+        wander = 0.05
+
+        [[servers]]
+        name = "good.example"
+
+        [[servers]]
+        name = "distant.example"
+        delay = 0.08
+        jitter = 0.01
+
+        [[servers]]
+        name = "asymmetric.example"
+        delay = 0.03
+        asymmetry = 0.02
+
+        [[servers]]
+        name = "flaky.example"
+        loss = 0.3
+        outages = [ [ 20000.0, 40000.0 ] ]
+
+        [[servers]]
+        name = "falseticker.example"
+        bias = 0.25
+        stratum = 3
+
+        [[steps]]
+        at = 50000.0
+        amount = -0.4
+    "#;
+
+    fn default_tgt_precision() -> f32 {
+        crate::sync::OffsetEstimator::DEFAULT_TGT_PRECISION
+    }
+
+    fn default_wakeup_itvl() -> f32 {
+        crate::sync::OffsetEstimator::DEFAULT_WAKEUP_ITVL
+    }
+
+    /// Read scenario description from a TOML document
+    pub fn from_toml(s: &str) -> Result<Scenario, ConfigReadError> {
+        let scen = toml::from_str::<Scenario>(s)
+                    .map_err(ConfigReadError::TomlError)?;
+
+        if scen.servers.is_empty() {
+            return Err(ConfigReadError::InvalidValue(
+                String::from("a scenario must contain at least one server")));
+        }
+
+        Ok(scen)
+    }
+
+    /// Read TOML scenario description from a supplied filesystem path
+    pub fn from_path(path: &Path) -> Result<Scenario, ConfigReadError> {
+        let raw = std::fs::read(path)?;
+        Scenario::from_toml(&String::from_utf8_lossy(&raw))
+    }
+
+    /// Synchronization settings for an estimator polling the simulated servers
+    pub fn sync_config(&self) -> SyncConfig {
+        SyncConfig {
+            ntp_servers: self.servers.iter().map(|s| s.name.clone()).collect(),
+            target_precision: self.target_precision,
+            wakeup_interval: self.wakeup_interval,
+            record_path: None,
+            watch_network: false,
+            ..SyncConfig::default()
+        }
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Scenario {
+        Scenario::from_toml(Scenario::DEFAULT_TOML)
+            .expect("Built-in scenario should be valid")
+    }
+}
+
+
+/// Simulated local clock and NTP servers, advancing in virtual time
+pub struct SimNetwork {
+    scenario: Scenario,
+    rng: SimRng,
+
+    /// The arbitrary origin of true time
+    epoch: Timestamp,
+
+    /// Elapsed true time since the epoch, in seconds
+    t_true: f64,
+
+    /// The accumulated random-walk and step error of the local clock, in seconds
+    wander: f64,
+
+    /// The number of clock-steps already applied
+    steps_applied: usize,
+
+    /// The number of suspensions already applied
+    suspensions_applied: usize,
+
+    /// The total suspended time not yet reported via take_suspension()
+    pending_suspension: f64,
+
+    /// The number of network changes already announced
+    network_changes_seen: usize,
+
+    /// The number of NTP requests issued
+    pub queries: u32,
+
+    /// The number of NTP requests which failed
+    pub failures: u32
+}
+
+impl SimNetwork {
+    /// The time waited for an NTP response before abandoning a request
+    const TIMEOUT: f64 = 2.5;
+
+    pub fn new(scenario: &Scenario) -> SimNetwork {
+        let mut scenario = scenario.clone();
+        scenario.steps.sort_by(|a, b| a.at.total_cmp(&b.at));
+        scenario.suspensions.sort_by(|a, b| a.at.total_cmp(&b.at));
+        scenario.network_changes.sort_by(|a, b| a.total_cmp(b));
+
+        SimNetwork {
+            rng: SimRng::new(scenario.seed),
+            scenario,
+            epoch: Timestamp::from_timestamp(1_000_000_000, 0).unwrap(),
+            t_true: 0.0,
+            wander: 0.0,
+            steps_applied: 0,
+            suspensions_applied: 0,
+            pending_suspension: 0.0,
+            network_changes_seen: 0,
+            queries: 0,
+            failures: 0
+        }
+    }
+
+    /// Elapsed true time since the start of the simulation, in seconds
+    pub fn elapsed(&self) -> f64 {
+        self.t_true
+    }
+
+    /// The current error of the local clock relative to true time, in seconds
+    pub fn clock_error(&self) -> f64 {
+        let osc = &self.scenario.oscillator;
+        osc.initial_error + osc.drift_ppm * 1e-6 * self.t_true + self.wander
+    }
+
+    /// Move true time forwards, accumulating random-walk and step errors
+    fn advance(&mut self, dt: f64) {
+        let dt = dt.max(0.0);
+        let wander = self.scenario.oscillator.wander;
+        if wander > 0.0 {
+            self.wander += wander * (dt / 86400.0).sqrt() * self.rng.gaussian();
+        }
+        self.t_true += dt;
+
+        while let Some(susp) = self.scenario.suspensions.get(self.suspensions_applied) {
+            if susp.at > self.t_true { break; }
+            self.t_true += susp.duration;
+            self.wander += susp.clock_jump;
+            self.pending_suspension += susp.duration;
+            self.suspensions_applied += 1;
+        }
+
+        while let Some(step) = self.scenario.steps.get(self.steps_applied) {
+            if step.at > self.t_true { break; }
+            self.wander += step.amount;
+            self.steps_applied += 1;
+        }
+    }
+
+    /// Convert a time, in seconds relative to the epoch, into a timestamp
+    fn to_timestamp(&self, t: f64) -> Timestamp {
+        self.epoch + chrono::Duration::nanoseconds((t * 1e9).round() as i64)
+    }
+}
+
+impl TimeSource for SimNetwork {
+    fn now(&self) -> Timestamp {
+        self.to_timestamp(self.t_true + self.clock_error())
+    }
+
+    fn ntp_ping(&mut self, server: &str) -> Result<NtpExchange, NtpError> {
+        self.queries += 1;
+
+        let idx = self.scenario.servers.iter().position(|s| s.name == server);
+        let (idx, model) = match idx {
+            Some(i) => (i, self.scenario.servers[i].clone()),
+            None => {
+                self.failures += 1;
+                return Err(NtpError::AddressResolve);
+            }
+        };
+
+        let d_out = model.delay + 0.5 * model.asymmetry
+                        + self.rng.exponential(model.jitter);
+        let d_in = model.delay - 0.5 * model.asymmetry
+                        + self.rng.exponential(model.jitter);
+        let lost = self.rng.uniform() < model.loss;
+
+        if lost || !model.is_reachable(self.t_true)
+                || d_out + d_in > SimNetwork::TIMEOUT {
+            self.advance(SimNetwork::TIMEOUT);
+            self.failures += 1;
+            return Err(NtpError::Timeout);
+        }
+
+        // Local and server timestamps, following RFC-5905 notation:
+        let t1 = self.now();
+        self.advance(d_out);
+        let t2 = self.to_timestamp(self.t_true + model.bias);
+        let t3 = t2;
+        self.advance(d_in);
+        let t4 = self.now();
+
+        Ok(NtpExchange {
+            server: model.name,
+            address: SocketAddr::from(([ 192, 0, 2, (idx % 250) as u8 + 1 ],
+                                       crate::ntp::NTP_PORT)),
+            leap: 0,
+            stratum: model.stratum,
+            precision: model.precision,
+            ref_id: u32::from_be_bytes(*b"SIM\0"),
+            t_originate: t1,
+            t_receive: t2,
+            t_transmit: t3,
+            t_destination: t4
+        })
+    }
+
+    fn sleep(&mut self, dt: std::time::Duration) {
+        // Durations are measured by the local clock, which runs at the drifting rate:
+        let rate = 1.0 + self.scenario.oscillator.drift_ppm * 1e-6;
+        let mut dt = dt.as_secs_f64() / rate;
+
+        // Wake early if the network changes during the sleep:
+        if let Some(&t_change) = self.scenario.network_changes.get(self.network_changes_seen) {
+            dt = dt.min(t_change - self.t_true);
+        }
+        self.advance(dt);
+    }
+
+    fn take_network_change(&mut self) -> bool {
+        let changes = &self.scenario.network_changes;
+        let n_past = changes.iter().filter(|&&t| t <= self.t_true).count();
+
+        std::mem::replace(&mut self.network_changes_seen, n_past) < n_past
+    }
+
+    fn choose_server(&mut self, count: usize) -> usize {
+        (self.rng.next_u64() % count as u64) as usize
+    }
+
+    fn take_suspension(&mut self) -> Option<std::time::Duration> {
+        let pending = std::mem::take(&mut self.pending_suspension);
+        (pending > 0.0).then(|| std::time::Duration::from_secs_f64(pending))
+    }
+}
+
+
+/// Summary of the accuracy of an estimator over a simulated scenario
+#[derive(Clone, Debug, Default)]
+pub struct SimReport {
+    /// The number of polling cycles
+    pub cycles: u32,
+
+    /// The number of NTP requests issued
+    pub queries: u32,
+
+    /// The number of NTP requests which failed
+    pub failures: u32,
+
+    /// The number of system suspensions detected by the estimator
+    pub resumptions: u32,
+
+    /// The number of network changes which triggered resynchronization
+    pub network_changes: u32,
+
+    /// The root-mean-square error of the estimated offset, in seconds
+    pub rms_error: f64,
+
+    /// The largest absolute error of the estimated offset, in seconds
+    pub max_error: f64,
+
+    /// The mean claimed standard-deviation of the offset, in seconds
+    pub mean_stddev: f64,
+
+    /// The fraction of cycles where the error was within one claimed standard-deviation
+    pub coverage_1sigma: f64,
+
+    /// The fraction of cycles where the error was within two claimed standard-deviations
+    pub coverage_2sigma: f64
+}
+
+impl std::fmt::Display for SimReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Polling cycles:      {}", self.cycles)?;
+        writeln!(f, "NTP queries:         {} ({} failed)",
+                 self.queries, self.failures)?;
+        writeln!(f, "Resumptions:         {}", self.resumptions)?;
+        writeln!(f, "Network changes:     {}", self.network_changes)?;
+        writeln!(f, "RMS offset error:    {:.3}ms", self.rms_error * 1e3)?;
+        writeln!(f, "Max offset error:    {:.3}ms", self.max_error * 1e3)?;
+        writeln!(f, "Mean claimed stddev: {:.3}ms", self.mean_stddev * 1e3)?;
+        writeln!(f, "Coverage (1-sigma):  {:.1}% (ideal 68.3%)",
+                 self.coverage_1sigma * 1e2)?;
+        write!(f, "Coverage (2-sigma):  {:.1}% (ideal 95.4%)",
+               self.coverage_2sigma * 1e2)
+    }
+}
+
+
+/// Drive an offset-estimator through a simulated scenario, comparing its
+/// estimates against the true clock error after every polling cycle
+pub fn run_scenario(scenario: &Scenario) -> SimReport {
+    let mut net = SimNetwork::new(scenario);
+    let mut tracker = OffsetTracker::new(&scenario.sync_config(),
+                                        &scenario.estimator);
+
+    let mut report = SimReport::default();
+    let (mut sum_sq, mut sum_sd) = (0.0, 0.0);
+    let (mut within1, mut within2) = (0, 0);
+
+    while net.elapsed() < scenario.duration {
+        let (offs, pause) = tracker.step(&mut net);
+
+        let estimate = offs.avg_offset.num_nanoseconds()
+                           .unwrap_or(0) as f64 * 1e-9;
+        // The estimator's offset should cancel the local clock error:
+        let err = (estimate + net.clock_error()).abs();
+        let sd = offs.stddev_offset as f64;
+
+        report.cycles += 1;
+        sum_sq += err * err;
+        sum_sd += sd;
+        report.max_error = report.max_error.max(err);
+        if err <= sd { within1 += 1; }
+        if err <= 2.0 * sd { within2 += 1; }
+
+        net.sleep(pause);
+    }
+
+    if report.cycles > 0 {
+        let n = report.cycles as f64;
+        report.rms_error = (sum_sq / n).sqrt();
+        report.mean_stddev = sum_sd / n;
+        report.coverage_1sigma = within1 as f64 / n;
+        report.coverage_2sigma = within2 as f64 / n;
+    }
+    report.queries = net.queries;
+    report.failures = net.failures;
+    report.resumptions = tracker.resumptions();
+    report.network_changes = tracker.network_changes();
+
+    report
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    fn quiet_scenario() -> Scenario {
+        Scenario::from_toml(r#"
+            duration = 3600.0
+            seed = 3
+            [oscillator]
+            initial_error = 0.75
+            [[servers]]
+            name = "a.example"
+            [[servers]]
+            name = "b.example"
+            "#).unwrap()
+    }
+
+    #[test]
+    fn rng_dist() {
+        const N: i32 = 20000;
+        let mut rng = SimRng::new(5);
+
+        let samples: Vec<f64> = (0 .. N).map(|_| rng.gaussian()).collect();
+        let mean = samples.iter().sum::<f64>() / (N as f64);
+        let vrnc = samples.iter().map(|x| x * x).sum::<f64>() / (N as f64)
+                        - mean * mean;
+
+        assert_close(mean, 0.0, 4.0 / (N as f64).sqrt());
+        assert_close(vrnc, 1.0, 0.05);
+
+        let expo = (0 .. N).map(|_| rng.exponential(2.5)).sum::<f64>() / (N as f64);
+        assert_close(expo, 2.5, 0.1);
+
+        assert_eq!(SimRng::new(9).next_u64(), SimRng::new(9).next_u64());
+    }
+
+    #[test]
+    fn default_scenario() {
+        let scen = Scenario::default();
+
+        assert_eq!(scen.servers.len(), 5);
+        assert_eq!(scen.sync_config().ntp_servers[1], "distant.example");
+        assert_eq!(scen.steps.len(), 1);
+        assert_eq!(scen.servers[0].stratum, 2);
+
+        assert!(matches!(Scenario::from_toml(r#"
+                            duration = 60.0
+                            servers = []
+                            [oscillator]
+                            initial_error = 0.1"#),
+                         Err(ConfigReadError::InvalidValue(_))));
+    }
+
+    #[test]
+    fn symmetric_exchange() {
+        let mut scen = quiet_scenario();
+        scen.servers[0].jitter = 1e-9;
+        let mut net = SimNetwork::new(&scen);
+
+        let sample = net.ntp_ping("a.example").unwrap();
+        assert_close(sample.offset(), -0.75, 1e-6);
+        assert_close(sample.roundtrip(), 0.02, 1e-6);
+        assert_eq!(net.queries, 1);
+
+        assert!(net.ntp_ping("unknown.example").is_err());
+        assert_eq!(net.failures, 1);
+    }
+
+    #[test]
+    fn asymmetric_exchange() {
+        let mut scen = quiet_scenario();
+        scen.servers[0].jitter = 1e-9;
+        scen.servers[0].asymmetry = 0.004;
+        let mut net = SimNetwork::new(&scen);
+
+        let sample = net.ntp_ping("a.example").unwrap();
+        assert_close(sample.offset(), -0.75 + 0.002, 1e-6);
+    }
+
+    #[test]
+    fn outages() {
+        let mut scen = quiet_scenario();
+        scen.servers[1].outages = vec![ (0.0, 100.0) ];
+        let mut net = SimNetwork::new(&scen);
+
+        assert!(net.ntp_ping("b.example").is_err());
+        assert_close(net.elapsed(), SimNetwork::TIMEOUT, 1e-9);
+        net.sleep(std::time::Duration::from_secs(100));
+        assert!(net.ntp_ping("b.example").is_ok());
+    }
+
+    #[test]
+    fn clock_steps() {
+        let mut scen = quiet_scenario();
+        scen.steps = vec![ ClockStep { at: 50.0, amount: 0.25 } ];
+        let mut net = SimNetwork::new(&scen);
+
+        assert_close(net.clock_error(), 0.75, 1e-12);
+        net.sleep(std::time::Duration::from_secs(60));
+        assert_close(net.clock_error(), 1.0, 1e-12);
+    }
+
+    #[test]
+    fn reproducibility() {
+        let scen = Scenario { duration: 3600.0, ..Scenario::default() };
+        let history = || {
+            let mut net = SimNetwork::new(&scen);
+            let mut tracker = OffsetTracker::new(&scen.sync_config(), &scen.estimator);
+            let mut events = vec![];
+
+            while net.elapsed() < scen.duration {
+                let (offs, pause) = tracker.step(&mut net);
+                events.push(( net.now(), offs.avg_offset, offs.stddev_offset ));
+                net.sleep(pause);
+            }
+
+            ( events, net.queries, net.failures )
+        };
+
+        let (events, queries, failures) = history();
+        assert!(failures > 0 && failures < queries);
+        assert_eq!(history(), (events, queries, failures));
+    }
+
+    #[test]
+    fn suspension() {
+        let mut scen = quiet_scenario();
+        scen.suspensions = vec![ Suspension { at: 1800.0, duration: 600.0,
+                                              clock_jump: 0.8 } ];
+        let mut net = SimNetwork::new(&scen);
+
+        net.sleep(std::time::Duration::from_secs(1790));
+        assert!(net.take_suspension().is_none());
+        net.sleep(std::time::Duration::from_secs(20));
+        assert_close(net.elapsed(), 2410.0, 1e-6);
+        assert_close(net.take_suspension().unwrap().as_secs_f64(), 600.0, 1e-9);
+        assert!(net.take_suspension().is_none());
+        assert_close(net.clock_error(), 1.55, 1e-9);
+
+        // After resumption, the estimator should repeat its warmup burst:
+        let mut net = SimNetwork::new(&scen);
+        let mut tracker = OffsetTracker::new(&scen.sync_config(), &scen.estimator);
+        let mut pauses = vec![];
+        while net.elapsed() < 3000.0 {
+            let (_, pause) = tracker.step(&mut net);
+            pauses.push(( net.elapsed(), pause.as_secs_f64() ));
+            net.sleep(pause);
+        }
+        let short: Vec<f64> = pauses.iter().filter(|(_, p)| *p < 1.0)
+                                    .map(|(t, _)| *t).collect();
+        assert_eq!(short.len(), 2 * OffsetTracker::WARMUP_CYCLES as usize);
+        assert!(short[OffsetTracker::WARMUP_CYCLES as usize] > 2400.0);
+        assert_eq!(tracker.resumptions(), 1);
+
+        let report = run_scenario(&scen);
+        assert_eq!(report.resumptions, 1);
+        assert!(report.coverage_2sigma > 0.9);
+    }
+
+    #[test]
+    fn network_changes() {
+        let mut scen = quiet_scenario();
+        scen.network_changes = vec![ 1000.0, 1000.5, 2000.0 ];
+        let mut net = SimNetwork::new(&scen);
+        let mut tracker = OffsetTracker::new(&scen.sync_config(), &scen.estimator);
+        let mut bursts = vec![];
+
+        while net.elapsed() < 3000.0 {
+            let (_, pause) = tracker.step(&mut net);
+            if pause.as_secs_f64() < 1.0 {
+                bursts.push(net.elapsed());
+            }
+            net.sleep(pause);
+        }
+
+        // A change should interrupt the sleep and trigger an immediate burst:
+        assert_eq!(tracker.network_changes(), 2);
+        assert!(bursts.iter().any(|&t| (1000.0 .. 1001.0).contains(&t)));
+        assert!(bursts.iter().any(|&t| (2000.0 .. 2001.0).contains(&t)));
+        assert_eq!(bursts.len(), 3 * OffsetTracker::WARMUP_CYCLES as usize);
+    }
+
+    #[test]
+    fn quiet_convergence() {
+        let report = run_scenario(&quiet_scenario());
+
+        assert!(report.cycles > 300);
+        assert!(report.queries >= 3 && report.queries < report.cycles);
+        assert_eq!(report.failures, 0);
+        assert!(report.rms_error < 0.1);
+        assert!(report.coverage_2sigma > 0.9);
+    }
+}
+
+// (C)Copyright 2026, RW Penney
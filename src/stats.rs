@@ -4,6 +4,7 @@
  */
 
 use chrono;
+use std::collections::HashMap;
 use crate::Timestamp;
 
 
@@ -81,13 +82,22 @@ impl BayesOffset {
     /// The minimum credible uncertainty in a clock-offset measurement (in seconds)
     const MIN_PRECISION: f32 = 1e-6;
 
+    /// The default growth rate of offset uncertainty, in seconds per square-root day
+    pub const DEFAULT_DIFFUSIVITY: f32 = 0.5;
+
     /// Create a new offset-estimator with zero bias and given standard-deviation
     pub fn new(dt0: f32) -> BayesOffset {
+        BayesOffset::with_diffusivity(dt0, BayesOffset::DEFAULT_DIFFUSIVITY)
+    }
+
+    /// Create a new offset-estimator with given standard-deviation
+    /// and rate of diffusive growth in uncertainty
+    pub fn with_diffusivity(dt0: f32, diffusivity: f32) -> BayesOffset {
         BayesOffset {
             mean: 0.0,
             variance: BayesOffset::clamp_variance(dt0),
             last_obs_time: None,
-            diffusivity: 0.5
+            diffusivity
         }
     }
 
@@ -104,6 +114,18 @@ impl BayesOffset {
         self.last_obs_time = Some(obs_time);
     }
 
+    /// Widen the posterior distribution by an additional independent
+    /// uncertainty (in seconds), e.g. after an unobserved disruption to the clock
+    pub fn inflate(&mut self, stddev: f32) {
+        self.variance += stddev * stddev;
+    }
+
+    /// Predictive mean and variance, in seconds and square-seconds,
+    /// of the clock offset at the given time, before any new observation
+    pub fn predict(&self, obs_time: Timestamp) -> (f32, f32) {
+        ( self.mean, self.diffused_variance(obs_time) )
+    }
+
     /// Maximum-likelihood estimator of the clock offset
     pub fn avg_offset(&self) -> chrono::Duration {
         chrono::Duration::microseconds((self.mean * 1e6) as i64)
@@ -137,10 +159,222 @@ impl BayesOffset {
 }
 
 
+/// Online weighted linear regression of NTP measurement error
+/// against round-trip time, for a single server
+#[derive(Clone, Debug, Default)]
+pub struct RttRegression {
+    /// Exponentially weighted sums of 1, x, y, x^2 and x*y
+    sw: f64,
+    sx: f64,
+    sy: f64,
+    sxx: f64,
+    sxy: f64,
+
+    /// The total number of samples supplied
+    samples: u32
+}
+
+impl RttRegression {
+    /// The weight given to each new sample, relative to the accumulated history
+    const EPS: f64 = 0.05;
+
+    /// The number of samples required before a fit is trusted
+    const MIN_SAMPLES: u32 = 8;
+
+    /// The minimum spread in round-trip times, in seconds,
+    /// needed to resolve the dependence of error on round-trip time
+    const MIN_RTT_SPREAD: f64 = 1e-3;
+
+    /// The smallest credible error at zero round-trip time, in seconds,
+    /// allowing for timestamping jitter within the client and server
+    const MIN_FLOOR: f64 = 1e-4;
+
+    /// Incorporate a new estimate of the measurement error at a given round-trip time
+    pub fn add_sample(&mut self, roundtrip: f32, error: f32) {
+        let (x, y) = (roundtrip as f64, error as f64);
+        let decay = 1.0 - RttRegression::EPS;
+
+        self.sw = decay * self.sw + 1.0;
+        self.sx = decay * self.sx + x;
+        self.sy = decay * self.sy + y;
+        self.sxx = decay * self.sxx + x * x;
+        self.sxy = decay * self.sxy + x * y;
+        self.samples += 1;
+    }
+
+    /// The fitted scale-factor and floor, such that error ~ scale * roundtrip + floor,
+    /// or None if there are too few samples
+    pub fn fit(&self, default_scale: f32) -> Option<(f32, f32)> {
+        if self.samples < RttRegression::MIN_SAMPLES {
+            return None;
+        }
+
+        let mean_x = self.sx / self.sw;
+        let mean_y = self.sy / self.sw;
+        let var_x = self.sxx / self.sw - mean_x * mean_x;
+
+        // The offset error cannot exceed half the round-trip time:
+        let scale = if var_x > RttRegression::MIN_RTT_SPREAD.powi(2) {
+            ((self.sxy / self.sw - mean_x * mean_y) / var_x).clamp(0.0, 0.5)
+        } else {
+            default_scale as f64
+        };
+        let floor = (mean_y - scale * mean_x).max(RttRegression::MIN_FLOOR);
+
+        Some(( scale as f32, floor as f32 ))
+    }
+}
+
+
+/// Ordinary least-squares fit of a straight line, e.g. of clock-offset against time
+#[derive(Clone, Debug, Default)]
+pub struct LinearFit {
+    /// Sums of 1, x, y, x^2, x*y and y^2
+    n: f64,
+    sx: f64,
+    sy: f64,
+    sxx: f64,
+    sxy: f64,
+    syy: f64
+}
+
+impl LinearFit {
+    pub fn add_sample(&mut self, x: f64, y: f64) {
+        self.n += 1.0;
+        self.sx += x;
+        self.sy += y;
+        self.sxx += x * x;
+        self.sxy += x * y;
+        self.syy += y * y;
+    }
+
+    /// The number of samples supplied
+    pub fn samples(&self) -> u32 {
+        self.n as u32
+    }
+
+    /// The fitted slope and intercept, or None if the x-values have no spread
+    pub fn fit(&self) -> Option<(f64, f64)> {
+        let var_x = self.sxx - self.sx * self.sx / self.n;
+
+        if self.n < 2.0 || var_x <= 0.0 {
+            return None;
+        }
+
+        let slope = (self.sxy - self.sx * self.sy / self.n) / var_x;
+
+        Some(( slope, (self.sy - slope * self.sx) / self.n ))
+    }
+
+    /// The standard error of the fitted slope, or None if there are fewer than three samples
+    pub fn slope_stddev(&self) -> Option<f64> {
+        let (slope, _) = self.fit()?;
+
+        if self.n < 3.0 {
+            return None;
+        }
+
+        let var_x = self.sxx - self.sx * self.sx / self.n;
+        let var_y = self.syy - self.sy * self.sy / self.n;
+        let resid = (var_y - slope * slope * var_x).max(0.0) / (self.n - 2.0);
+
+        Some((resid / var_x).sqrt())
+    }
+}
+
+
+/// Linearly interpolated quantile of a sorted sequence
+pub fn quantile(sorted: &[f64], q: f64) -> Option<f64> {
+    let pos = q.clamp(0.0, 1.0) * (sorted.len().checked_sub(1)? as f64);
+    let idx = pos.floor() as usize;
+    let frac = pos - idx as f64;
+
+    match sorted.get(idx + 1) {
+        Some(next) => Some(sorted[idx] + frac * (next - sorted[idx])),
+        None => Some(sorted[idx])
+    }
+}
+
+
+/// Fitted relationship between offset error and round-trip time for one server
+#[derive(Clone, Debug, PartialEq)]
+pub struct RttFit {
+    pub server: String,
+
+    /// The ratio of offset error to round-trip time
+    pub scale: f32,
+
+    /// The offset error at zero round-trip time, in seconds
+    pub floor: f32,
+
+    /// The number of observations contributing to the fit
+    pub samples: u32
+}
+
+
+/// Per-server calibration of NTP measurement precision,
+/// learned from the innovations of a Bayesian offset estimator
+#[derive(Clone, Debug)]
+pub struct ErrorCalibrator {
+    /// The ratio of offset error to round-trip time assumed before calibration
+    default_scale: f32,
+
+    servers: HashMap<String, RttRegression>
+}
+
+impl ErrorCalibrator {
+    pub fn new(default_scale: f32) -> ErrorCalibrator {
+        ErrorCalibrator {
+            default_scale,
+            servers: HashMap::new()
+        }
+    }
+
+    /// The expected margin of error of an offset measured with the given
+    /// round-trip time, and server clock-precision (as log2(seconds))
+    pub fn precision(&self, server: &str, roundtrip: f32, clock_prec: i8) -> f32 {
+        let clock_err = 2.0f32.powi(clock_prec as i32);
+
+        match self.servers.get(server).and_then(|r| r.fit(self.default_scale)) {
+            Some((scale, floor)) => (scale * roundtrip + floor).max(clock_err),
+            None => self.default_scale * roundtrip + clock_err
+        }
+    }
+
+    /// Incorporate the difference between a measured offset and the
+    /// estimator's prediction, given the variance of that prediction
+    pub fn add_innovation(&mut self, server: &str, roundtrip: f32,
+                          innovation: f32, prior_var: f32) {
+        // Remove the predictive uncertainty, and convert the remaining
+        // absolute error into a Gaussian standard-deviation:
+        let excess = (innovation * innovation - prior_var).max(0.0).sqrt();
+        let error = excess * std::f32::consts::FRAC_PI_2.sqrt();
+
+        self.servers.entry(String::from(server))
+                    .or_default()
+                    .add_sample(roundtrip, error);
+    }
+
+    /// The current fitted parameters for all servers with sufficient data
+    pub fn fits(&self) -> Vec<RttFit> {
+        let mut fits: Vec<RttFit> =
+            self.servers.iter()
+                .filter_map(|(server, reg)|
+                    reg.fit(self.default_scale).map(|(scale, floor)| RttFit {
+                        server: server.clone(), scale, floor,
+                        samples: reg.samples }))
+                .collect();
+        fits.sort_by(|a, b| a.server.cmp(&b.server));
+
+        fits
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
     use chrono::Duration;
-    use super::{ BayesOffset, ExpoAvg };
+    use super::{ BayesOffset, ErrorCalibrator, ExpoAvg, LinearFit, RttRegression, quantile };
     use crate::utc_now;
     use crate::testing::*;
 
@@ -195,6 +429,88 @@ mod tests {
         assert_close(filter.query().unwrap(), 67e3, 1e-12);
     }
 
+    #[test]
+    fn rtt_regression() {
+        let mut reg = RttRegression::default();
+
+        for i in 0..40 {
+            let rtt = 0.01 + 0.002 * (i % 7) as f32;
+            reg.add_sample(rtt, 0.3 * rtt + 0.004);
+            assert_eq!(reg.fit(0.25).is_some(), i + 1 >= RttRegression::MIN_SAMPLES);
+        }
+
+        let (scale, floor) = reg.fit(0.25).unwrap();
+        assert_close(scale as f64, 0.3, 1e-4);
+        assert_close(floor as f64, 0.004, 1e-5);
+
+        let mut flat = RttRegression::default();
+        for _ in 0..10 {
+            flat.add_sample(0.02, 0.01);
+        }
+        let (scale, floor) = flat.fit(0.25).unwrap();
+        assert_eq!(scale, 0.25);
+        assert_close(floor as f64, 0.005, 1e-6);
+    }
+
+    #[test]
+    fn linear_fit() {
+        let mut lin = LinearFit::default();
+        lin.add_sample(3.0, 1.0);
+        assert_eq!(lin.fit(), None);
+
+        for i in 1..20 {
+            let x = 3.0 + 10.0 * i as f64;
+            lin.add_sample(x, 1.0 - 2e-5 * (x - 3.0) + 1e-4 * ((i % 3) as f64 - 1.0));
+        }
+
+        let (slope, intercept) = lin.fit().unwrap();
+        assert_eq!(lin.samples(), 20);
+        assert_close(slope, -2e-5, 1e-6);
+        assert_close(intercept, 1.0 + 6e-5, 1e-4);
+        assert!(lin.slope_stddev().unwrap() < 5e-6);
+    }
+
+    #[test]
+    fn quantiles() {
+        let sorted = [ 1.0, 2.0, 4.0, 8.0, 16.0 ];
+
+        assert_eq!(quantile(&[], 0.5), None);
+        assert_eq!(quantile(&sorted, 0.0), Some(1.0));
+        assert_eq!(quantile(&sorted, 0.5), Some(4.0));
+        assert_eq!(quantile(&sorted, 0.625), Some(6.0));
+        assert_eq!(quantile(&sorted, 1.0), Some(16.0));
+    }
+
+    #[test]
+    fn error_calibration() {
+        let mut cal = ErrorCalibrator::new(0.25);
+
+        assert_close(cal.precision("a", 0.04, -10) as f64,
+                     0.01 + 2f64.powi(-10), 1e-9);
+
+        for i in 0..50 {
+            let rtt = 0.01 + 0.003 * (i % 5) as f32;
+            let err = 0.1 * rtt + 0.001;
+            let sign = if i % 2 == 0 { 1.0 } else { -1.0 };
+            cal.add_innovation("a", rtt,
+                               sign * err / std::f32::consts::FRAC_PI_2.sqrt(), 0.0);
+        }
+
+        let fits = cal.fits();
+        assert_eq!(fits.len(), 1);
+        assert_eq!(fits[0].server, "a");
+        assert_eq!(fits[0].samples, 50);
+        assert_close(fits[0].scale as f64, 0.1, 1e-3);
+        assert_close(cal.precision("a", 0.04, -20) as f64, 0.005, 1e-4);
+        assert_close(cal.precision("b", 0.04, -20) as f64, 0.01, 1e-5);
+
+        // Consistently perfect measurements should not imply unbounded confidence:
+        for _ in 0..20 {
+            cal.add_innovation("c", 0.02, 0.0, 0.0);
+        }
+        assert_close(cal.precision("c", 0.0, -30) as f64, 1e-4, 1e-9);
+    }
+
     #[test]
     fn bo_init() {
         let bo0 = BayesOffset::new(1.5);
@@ -219,6 +535,16 @@ mod tests {
         assert_eq!(bo.avg_offset(), chrono::Duration::milliseconds(125));
     }
 
+    #[test]
+    fn bo_inflation() {
+        let mut bo = BayesOffset::new(0.3);
+        bo.mean = 0.2;
+        bo.inflate(0.4);
+
+        assert_eq!(bo.mean, 0.2);
+        assert_close(bo.stddev_offset(utc_now()) as f64, 0.5, 1e-6);
+    }
+
     #[test]
     fn bo_variances() {
         let mut bo = BayesOffset::new(2.5);
@@ -234,6 +560,18 @@ mod tests {
         assert_close(bo.stddev_offset(t1) as f64, (15.25f64).sqrt(), 1e-7);
     }
 
+    #[test]
+    fn bo_prediction() {
+        let mut bo = BayesOffset::with_diffusivity(0.1, 2.0);
+        let t0 = mk_time(0, (0, 0, 0));
+
+        bo.add_observation(0.3, 0.1, t0);
+        let (mean, var) = bo.predict(t0 + chrono::Duration::hours(6));
+
+        assert_close(mean as f64, 0.15, 1e-7);
+        assert_close(var as f64, 0.005 + 1.0, 1e-6);
+    }
+
     #[test]
     fn bo_simple_update() {
         const PRECISION: f32 = 1.7e-2;
@@ -0,0 +1,303 @@
+/*
+ *  Comparative probing of candidate NTP servers for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use serde::Serialize;
+use std::time::Duration;
+use crate::{
+    ntp::NtpExchange,
+    sync::TimeSource };
+
+
+/// Summary of repeated NTP exchanges with a single server,
+/// with all times in seconds
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ServerSurvey {
+    pub server: String,
+
+    /// The number of requests sent
+    pub attempts: u32,
+
+    /// The number of valid responses received
+    pub responses: u32,
+
+    pub rtt_min: Option<f64>,
+    pub rtt_median: Option<f64>,
+    pub rtt_max: Option<f64>,
+
+    /// The mean correction to be added to the local clock
+    pub offset_mean: Option<f64>,
+
+    /// The standard deviation of the individual offsets
+    pub offset_spread: Option<f64>,
+
+    /// The stratum reported in the latest response
+    pub stratum: Option<u8>,
+
+    /// The reference identifier reported in the latest response
+    pub ref_id: Option<String>,
+
+    /// The leap indicator reported in the latest response
+    /// (0 = none, 1 = +1s, 2 = -1s, 3 = unsynchronized)
+    pub leap: Option<u8>,
+
+    /// The excess of the mean offset over the consensus of all servers
+    pub deviation: Option<f64>,
+
+    /// Whether the deviation exceeds that attributable to path asymmetry and jitter
+    pub outlier: bool
+}
+
+impl ServerSurvey {
+    /// The smallest deviation from consensus which is flagged as an outlier, in seconds
+    pub const MIN_TOLERANCE: f64 = 0.001;
+
+    fn from_exchanges(server: &str, attempts: u32, exchanges: &[NtpExchange]) -> ServerSurvey {
+        let mut survey = ServerSurvey { server: String::from(server), attempts,
+                                        responses: exchanges.len() as u32,
+                                        ..ServerSurvey::default() };
+        let latest = match exchanges.last() {
+            Some(exch) => exch,
+            None => return survey
+        };
+
+        let mut rtts: Vec<f64> = exchanges.iter().map(|x| x.roundtrip()).collect();
+        rtts.sort_by(f64::total_cmp);
+        let offsets: Vec<f64> = exchanges.iter().map(|x| x.offset()).collect();
+        let n = offsets.len() as f64;
+        let mean = offsets.iter().sum::<f64>() / n;
+        let var = offsets.iter().map(|x| (x - mean).powi(2)).sum::<f64>()
+                        / (n - 1.0).max(1.0);
+
+        survey.rtt_min = rtts.first().copied();
+        survey.rtt_median = median(&rtts);
+        survey.rtt_max = rtts.last().copied();
+        survey.offset_mean = Some(mean);
+        survey.offset_spread = Some(var.sqrt());
+        survey.stratum = Some(latest.stratum);
+        survey.ref_id = Some(latest.ref_id_string());
+        survey.leap = Some(latest.leap);
+
+        survey
+    }
+
+    /// The largest deviation from consensus explicable by an asymmetric network path,
+    /// which may bias an offset by up to half the round-trip time, plus jitter
+    pub fn tolerance(&self) -> Option<f64> {
+        Some(0.5 * self.rtt_min? + 3.0 * self.offset_spread?
+                + ServerSurvey::MIN_TOLERANCE)
+    }
+
+    /// Short description of the leap indicator
+    pub fn leap_text(&self) -> &'static str {
+        match self.leap {
+            None =>     "-",
+            Some(0) =>  "none",
+            Some(1) =>  "+1s",
+            Some(2) =>  "-1s",
+            Some(_) =>  "unsync"
+        }
+    }
+}
+
+
+/// The middle value of a sorted sequence, or the mean of the two middle values
+fn median(sorted: &[f64]) -> Option<f64> {
+    let n = sorted.len();
+
+    match n {
+        0 => None,
+        _ if n % 2 == 1 => Some(sorted[n / 2]),
+        _ => Some(0.5 * (sorted[n / 2 - 1] + sorted[n / 2]))
+    }
+}
+
+
+/// Statistics on a set of servers, relative to their consensus offset
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct SurveyReport {
+    /// The median of the mean offsets of all responding servers, in seconds
+    pub consensus: Option<f64>,
+
+    pub servers: Vec<ServerSurvey>
+}
+
+impl SurveyReport {
+    /// Compare each server against the consensus of all servers
+    pub fn new(mut servers: Vec<ServerSurvey>) -> SurveyReport {
+        let mut means: Vec<f64> = servers.iter().filter_map(|s| s.offset_mean).collect();
+        means.sort_by(f64::total_cmp);
+        let consensus = median(&means);
+
+        for srv in servers.iter_mut() {
+            srv.deviation = consensus.zip(srv.offset_mean).map(|(c, m)| m - c);
+            srv.outlier = match ( srv.deviation, srv.tolerance() ) {
+                ( Some(dev), Some(tol) ) => dev.abs() > tol,
+                _ => false
+            };
+        }
+
+        SurveyReport { consensus, servers }
+    }
+
+    /// The number of servers which responded at least once
+    pub fn reachable(&self) -> usize {
+        self.servers.iter().filter(|s| s.responses > 0).count()
+    }
+
+    /// Render as a text table, with times in milliseconds
+    pub fn format_table(&self) -> String {
+        let width = self.servers.iter().map(|s| s.server.len()).max()
+                                       .unwrap_or(0).max(6);
+        let ms = |x: Option<f64>| x.map_or(String::from("-"),
+                                           |v| format!("{:.3}", v * 1e3));
+
+        let mut table = format!("{:width$}  reach  rtt_min  rtt_med  rtt_max    offset   spread  deviation  stratum  ref_id           leap    status\n",
+                                "server", width = width);
+        for srv in self.servers.iter() {
+            table += &format!("{:width$}  {:>5}  {:>7}  {:>7}  {:>7}  {:>8}  {:>7}  {:>9}  {:>7}  {:16} {:7} {}\n",
+                              srv.server, format!("{}/{}", srv.responses, srv.attempts),
+                              ms(srv.rtt_min), ms(srv.rtt_median), ms(srv.rtt_max),
+                              ms(srv.offset_mean), ms(srv.offset_spread),
+                              ms(srv.deviation),
+                              srv.stratum.map_or(String::from("-"), |s| s.to_string()),
+                              srv.ref_id.as_deref().unwrap_or("-"),
+                              srv.leap_text(),
+                              if srv.outlier { "OUTLIER" }
+                              else if srv.responses == 0 { "unreachable" }
+                              else { "ok" },
+                              width = width);
+        }
+        if let Some(consensus) = self.consensus {
+            table += &format!("\nconsensus offset: {:.3}ms\n", consensus * 1e3);
+        }
+
+        table
+    }
+
+    /// Render as comma-separated values, with times in seconds
+    pub fn format_csv(&self) -> String {
+        let num = |x: Option<f64>| x.map_or(String::new(), |v| format!("{:.6}", v));
+        let mut csv = String::from("server,attempts,responses,rtt_min,rtt_median,rtt_max,offset_mean,offset_spread,deviation,stratum,ref_id,leap,outlier\n");
+
+        for srv in self.servers.iter() {
+            csv += &format!("{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                            csv_field(&srv.server), srv.attempts, srv.responses,
+                            num(srv.rtt_min), num(srv.rtt_median), num(srv.rtt_max),
+                            num(srv.offset_mean), num(srv.offset_spread),
+                            num(srv.deviation),
+                            srv.stratum.map_or(String::new(), |s| s.to_string()),
+                            csv_field(srv.ref_id.as_deref().unwrap_or("")),
+                            srv.leap.map_or(String::new(), |l| l.to_string()),
+                            srv.outlier);
+        }
+
+        csv
+    }
+
+    /// Render as a JSON object, with times in seconds
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Survey report should be serializable")
+    }
+}
+
+
+/// Quote a CSV field if it contains separators or quotes
+fn csv_field(txt: &str) -> String {
+    if txt.contains([ ',', '"', '\n' ]) {
+        format!("\"{}\"", txt.replace('"', "\"\""))
+    } else {
+        String::from(txt)
+    }
+}
+
+
+/// Query each server a given number of times, in interleaved rounds
+/// separated by the given interval
+pub fn survey<S: TimeSource>(mut src: S, servers: &[String], count: u32,
+                             interval: Duration) -> SurveyReport {
+    let mut exchanges: Vec<Vec<NtpExchange>> = vec![vec![]; servers.len()];
+
+    for round in 0 .. count {
+        if round > 0 {
+            src.sleep(interval);
+        }
+
+        for (server, exch) in servers.iter().zip(exchanges.iter_mut()) {
+            match src.ntp_ping(server) {
+                Ok(ping) => exch.push(ping),
+                Err(e) => log::debug!("No response from {} - {:?}", server, e)
+            }
+        }
+    }
+
+    SurveyReport::new(servers.iter().zip(exchanges.iter())
+                             .map(|(server, exch)| ServerSurvey::from_exchanges(server, count, exch))
+                             .collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ sim::{ Scenario, SimNetwork }, testing::* };
+
+    #[test]
+    fn medians() {
+        assert_eq!(median(&[]), None);
+        assert_eq!(median(&[ 3.0 ]), Some(3.0));
+        assert_eq!(median(&[ 1.0, 2.0, 4.0, 9.0 ]), Some(3.0));
+    }
+
+    #[test]
+    fn simulated_survey() {
+        let scen = Scenario::default();
+        let mut names = scen.sync_config().ntp_servers;
+        names.push(String::from("missing.example"));
+
+        let report = survey(SimNetwork::new(&scen), &names, 10, Duration::from_secs(1));
+        let find = |name: &str| report.servers.iter().find(|s| s.server == name).unwrap();
+
+        assert_eq!(report.servers.len(), names.len());
+        assert_eq!(report.reachable(), names.len() - 1);
+
+        let good = find("good.example");
+        assert_eq!(( good.responses, good.attempts ), ( 10, 10 ));
+        assert!(!good.outlier);
+        assert!(good.rtt_min <= good.rtt_median && good.rtt_median <= good.rtt_max);
+        assert_close(good.rtt_min.unwrap(), 0.02, 0.005);
+        assert_eq!(good.stratum, Some(2));
+        assert_eq!(good.leap_text(), "none");
+
+        let false_ticker = find("falseticker.example");
+        assert!(false_ticker.outlier);
+        assert_close(false_ticker.deviation.unwrap(), 0.25, 0.02);
+        assert_eq!(false_ticker.stratum, Some(3));
+
+        assert!(find("flaky.example").responses < 10);
+        assert!(!find("distant.example").outlier);
+
+        let missing = find("missing.example");
+        assert_eq!(missing.responses, 0);
+        assert_eq!(( missing.offset_mean, missing.outlier ), ( None, false ));
+
+        let table = report.format_table();
+        assert_eq!(table.matches("OUTLIER").count(), 1);
+        assert!(table.contains("unreachable"));
+
+        let csv = report.format_csv();
+        assert_eq!(csv.lines().count(), names.len() + 1);
+        assert!(csv.contains("\nmissing.example,10,0,,,,,,,,,,false\n"));
+        assert!(report.to_json().contains(r#""server": "falseticker.example""#));
+    }
+
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(csv_field("GPS"), "GPS");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("x\"y"), "\"x\"\"y\"");
+    }
+}
+
+// (C)Copyright 2026, RW Penney
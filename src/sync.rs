@@ -3,156 +3,387 @@
  *  RW Penney, May 2023
  */
 
-use sntpc::{ NtpContext, NtpResult, NtpTimestampGenerator, NtpUdpSocket };
 use std::{
-    net::{ SocketAddr, ToSocketAddrs, UdpSocket },
-    rc::Rc,
-    sync::mpsc,
-    thread };
+    path::PathBuf,
+    sync::{ Arc, atomic::{ AtomicBool, Ordering } } };
 use crate::{
-    OffsetEvent, Timestamp, UImessage, UIsender, utc_now, weak_rand,
-    config::SyncConfig,
-    stats::BayesOffset };
+    OffsetEvent, Timestamp, UImessage, utc_now, weak_rand,
+    audit::{ AuditLog, AuditingSource },
+    bus::{ EventBus, PublishingSource },
+    config::{ EstimatorConfig, SyncConfig },
+    eventlog::{ EventLog, LogEvent, LoggingSource, ResetReason },
+    netmon::{ self, NetworkEvents, NoNetworkEvents },
+    ntp::{ NtpClient, NtpError, NtpExchange },
+    record::RecordingSource,
+    stats::{ BayesOffset, ErrorCalibrator, RttFit } };
 
 
-#[derive(Clone, Copy, Default)]
-struct StdTimestampGen {
-    t: Timestamp
+/// Provider of local-clock readings and NTP measurements,
+/// allowing the estimator to be driven by real, simulated or recorded networks
+pub trait TimeSource {
+    /// The (uncorrected) time according to the local clock
+    fn now(&self) -> Timestamp;
+
+    /// Perform a single NTP exchange with the given server
+    fn ntp_ping(&mut self, server: &str) -> Result<NtpExchange, NtpError>;
+
+    /// Pause until the given interval has elapsed
+    fn sleep(&mut self, dt: std::time::Duration);
+
+    /// Choose which of the given number of servers to query next
+    fn choose_server(&mut self, count: usize) -> usize {
+        weak_rand() as usize % count
+    }
+
+    /// The duration of any system suspension since this was last called
+    fn take_suspension(&mut self) -> Option<std::time::Duration> {
+        None
+    }
+
+    /// Whether the network configuration has changed since this was last called
+    fn take_network_change(&mut self) -> bool {
+        false
+    }
 }
 
-impl NtpTimestampGenerator for StdTimestampGen {
-    fn init(&mut self) {
-        self.t = utc_now();
+/// Borrowed time-sources, so that a caller can inspect a source after use
+impl<S: TimeSource + ?Sized> TimeSource for &mut S {
+    fn now(&self) -> Timestamp {
+        (**self).now()
+    }
+
+    fn ntp_ping(&mut self, server: &str) -> Result<NtpExchange, NtpError> {
+        (**self).ntp_ping(server)
+    }
+
+    fn sleep(&mut self, dt: std::time::Duration) {
+        (**self).sleep(dt)
+    }
+
+    fn choose_server(&mut self, count: usize) -> usize {
+        (**self).choose_server(count)
     }
 
-    fn timestamp_sec(&self) -> u64 {
-        self.t.timestamp() as u64
+    fn take_suspension(&mut self) -> Option<std::time::Duration> {
+        (**self).take_suspension()
     }
 
-    fn timestamp_subsec_micros(&self) -> u32 {
-        self.t.timestamp_subsec_micros()
+    fn take_network_change(&mut self) -> bool {
+        (**self).take_network_change()
     }
 }
 
-#[derive(Clone, Debug)]
-struct UdpSocketWrapper {
-    skt: Rc<UdpSocket>
+
+/// Detector of system suspension, via growth in the difference between
+/// CLOCK_BOOTTIME (which includes time suspended) and CLOCK_MONOTONIC (which doesn't)
+#[derive(Clone, Debug, Default)]
+pub struct SuspendDetector {
+    /// The previous difference between the two clocks
+    baseline: Option<std::time::Duration>
 }
 
-impl NtpUdpSocket for UdpSocketWrapper {
-    fn send_to<T: ToSocketAddrs>(&self, buff: &[u8], addr: T) -> sntpc::Result<usize> {
-        match self.skt.send_to(buff, addr) {
-            Ok(usize) => Ok(usize),
-            Err(_) => Err(sntpc::Error::Network)
-        }
+impl SuspendDetector {
+    /// The smallest growth in clock difference regarded as a suspension
+    const MIN_SUSPENSION: std::time::Duration = std::time::Duration::from_secs(1);
+
+    pub fn new() -> SuspendDetector {
+        let mut detector = SuspendDetector::default();
+        detector.check();
+        detector
+    }
+
+    /// Report the time suspended since the previous check, if significant
+    pub fn check(&mut self) -> Option<std::time::Duration> {
+        let diff = SuspendDetector::boot_minus_monotonic()?;
+        let previous = self.baseline.replace(diff)?;
+
+        diff.checked_sub(previous)
+            .filter(|&gap| gap >= SuspendDetector::MIN_SUSPENSION)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn boot_minus_monotonic() -> Option<std::time::Duration> {
+        let read = |clock| {
+            let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+            let rc = unsafe { libc::clock_gettime(clock, &mut ts) };
+            (rc == 0).then(|| std::time::Duration::new(ts.tv_sec as u64,
+                                                       ts.tv_nsec as u32))
+        };
+
+        read(libc::CLOCK_BOOTTIME)?.checked_sub(read(libc::CLOCK_MONOTONIC)?)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn boot_minus_monotonic() -> Option<std::time::Duration> {
+        None
+    }
+}
+
+
+/// Live network of NTP servers, queried via a UDP socket
+pub struct SystemSource {
+    client: NtpClient,
+    suspend: SuspendDetector,
+
+    /// Any suspension noticed during a sleep, but not yet reported
+    suspended: Option<std::time::Duration>,
+
+    /// Notifications of changes to network addresses or routes
+    net_events: Box<dyn NetworkEvents + Send>,
+
+    /// Whether the network has changed since last reported
+    net_changed: bool,
+
+    /// Flag which, once set, cuts short any sleep
+    stop: Option<Arc<AtomicBool>>
+}
+
+impl SystemSource {
+    /// The time waited for an NTP response before abandoning a request
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_millis(2500);
+
+    /// The longest interval slept between checks for system suspension
+    const SUSPEND_POLL: std::time::Duration = std::time::Duration::from_secs(1);
+
+    pub fn new() -> SystemSource {
+        SystemSource::with_events(Box::new(NoNetworkEvents))
     }
 
-    fn recv_from(&self, buff: &mut [u8]) -> sntpc::Result<(usize, SocketAddr)> {
-        match self.skt.recv_from(buff) {
-            Ok((size, addr)) => Ok((size, addr)),
-            Err(_) => Err(sntpc::Error::Network)
+    /// Create a time-source whose sleeps are interrupted by network changes
+    pub fn with_events(net_events: Box<dyn NetworkEvents + Send>) -> SystemSource {
+        SystemSource {
+            client: SystemSource::new_client(),
+            suspend: SuspendDetector::new(),
+            suspended: None,
+            net_events,
+            net_changed: false,
+            stop: None
         }
     }
+
+    /// Cut short sleeps, within one slice, once the given flag is set
+    pub fn with_stop(mut self, stop: Arc<AtomicBool>) -> SystemSource {
+        self.stop = Some(stop);
+        self
+    }
+
+    fn new_client() -> NtpClient {
+        NtpClient::new(SystemSource::TIMEOUT)
+            .expect("Failed to prepare UDP socket")
+    }
 }
 
+impl Default for SystemSource {
+    fn default() -> SystemSource {
+        SystemSource::new()
+    }
+}
 
-pub struct OffsetEstimator {
-    tkr_channel: mpsc::Sender<OffsetEvent>,
-    ui_channel: UIsender,
+impl TimeSource for SystemSource {
+    fn now(&self) -> Timestamp {
+        utc_now()
+    }
+
+    fn ntp_ping(&mut self, server: &str) -> Result<NtpExchange, NtpError> {
+        // See https://datatracker.ietf.org/doc/html/rfc5905#section-7.3
+        self.client.query(server)
+    }
+
+    fn sleep(&mut self, dt: std::time::Duration) {
+        // The monotonic clock halts during suspension, so sleep in short slices,
+        // waking promptly on resumption rather than completing the full interval:
+        let t_end = std::time::Instant::now() + dt;
+
+        while let Some(remaining) = t_end.checked_duration_since(std::time::Instant::now())
+                                         .filter(|r| !r.is_zero()) {
+            if self.net_events.wait(remaining.min(SystemSource::SUSPEND_POLL)) {
+                // Use a fresh socket, bound via the new default route:
+                self.client = SystemSource::new_client();
+                self.net_changed = true;
+                return;
+            }
+
+            if let Some(gap) = self.suspend.check() {
+                self.suspended = Some(self.suspended.unwrap_or_default() + gap);
+                return;
+            }
 
+            if self.stop.as_ref().is_some_and(|s| s.load(Ordering::Relaxed)) {
+                return;
+            }
+        }
+    }
+
+    fn take_suspension(&mut self) -> Option<std::time::Duration> {
+        match ( self.suspended.take(), self.suspend.check() ) {
+            ( Some(earlier), Some(later) ) => Some(earlier + later),
+            ( earlier, later ) => earlier.or(later)
+        }
+    }
+
+    fn take_network_change(&mut self) -> bool {
+        std::mem::take(&mut self.net_changed)
+    }
+}
+
+
+/// Polling policy and statistical model of the clock-offset,
+/// independent of any threads or message channels
+pub struct OffsetTracker {
     /// Time between wakeups, in seconds
     wakeup_interval: f32,
 
     /// Bayesian statistical model of clock-offset
     stats: BayesOffset,
 
+    /// Per-server model of offset measurement error versus round-trip time
+    calibrator: ErrorCalibrator,
+
+    /// Whether to learn the relationship between measurement error and round-trip time
+    calibrate_rtt: bool,
+
     /// A collection of NTP server hostnames
     ntp_servers: Vec<String>,
 
     /// The desired maximum uncertainty in the clock-offset, in seconds
-    target_precision: f32
+    target_precision: f32,
+
+    /// The additional uncertainty in the clock-offset after a system suspension
+    resume_stddev: f32,
+
+    /// The number of polling cycles completed since startup or resumption
+    tick_count: u32,
+
+    /// The number of system suspensions detected
+    resumptions: u32,
+
+    /// The number of network changes which triggered resynchronization
+    network_changes: u32
 }
 
-impl OffsetEstimator {
-    pub const DEFAULT_TGT_PRECISION: f32 = 0.03;
-    pub const DEFAULT_WAKEUP_ITVL: f32 = 11.0;
+impl OffsetTracker {
+    /// The number of initial polling cycles which unconditionally query a server
+    pub const WARMUP_CYCLES: u32 = 3;
 
-    pub fn new(tkr_channel: mpsc::Sender<OffsetEvent>, ui_channel: UIsender,
-               config: &SyncConfig) -> OffsetEstimator {
-        OffsetEstimator {
-            tkr_channel,
-            ui_channel,
+    /// The default initial uncertainty in the clock-offset, in seconds
+    pub const DEFAULT_PRIOR_STDDEV: f32 = 30.0;
+
+    /// The default ratio of offset measurement error to round-trip time
+    pub const DEFAULT_RTT_FACTOR: f32 = 0.25;
+
+    /// The default additional uncertainty in the clock-offset after suspension, in seconds
+    pub const DEFAULT_RESUME_STDDEV: f32 = 2.0;
+
+    pub fn new(config: &SyncConfig, est_config: &EstimatorConfig) -> OffsetTracker {
+        OffsetTracker {
             wakeup_interval: config.wakeup_interval,
-            stats: BayesOffset::new(30.0),
+            stats: BayesOffset::with_diffusivity(est_config.prior_stddev,
+                                                 est_config.diffusivity),
+            calibrator: ErrorCalibrator::new(est_config.rtt_error_factor),
+            calibrate_rtt: est_config.calibrate_rtt,
             ntp_servers: config.ntp_servers.clone(),
-            target_precision: config.target_precision
+            target_precision: config.target_precision,
+            resume_stddev: est_config.resume_stddev,
+            tick_count: 0,
+            resumptions: 0,
+            network_changes: 0
         }
     }
 
-    /// Entry-point for clock-offset thread communicating via message queues
-    pub fn run(&mut self) {
-        let skt = UdpSocket::bind("0.0.0.0:0")
-                    .expect("Failed to bind UDP socket");
-        skt.set_read_timeout(Some(std::time::Duration::from_secs_f64(2.5)))
-           .expect("Failed to set UDP timeout");
-        let wrapped_skt = UdpSocketWrapper { skt: Rc::new(skt) };
-        let ntp_ctxt = NtpContext::new(StdTimestampGen::default());
-        let mut tick_count: u32 = 0;
-
-        loop {
-            let warmup = tick_count < 3;
-
-            let tick_time = self.check_precision(&wrapped_skt, &ntp_ctxt,
-                                                 warmup);
-            tick_count += 1;
-
-            let offs = OffsetEvent {
-                avg_offset: self.stats.avg_offset(),
-                stddev_offset: self.stats.stddev_offset(tick_time) };
-
-            self.tkr_channel.send(offs).unwrap();
-            self.ui_channel.send(UImessage::Offset(offs)).unwrap();
-
-            let pause = if !warmup {
-                std::time::Duration::from_secs_f32(self.wakeup_interval)
-            } else {
-                std::time::Duration::from_millis(500)
-            };
-            thread::sleep(pause);
+    /// Perform one polling cycle, returning the updated offset estimate
+    /// and the time to wait before the next cycle
+    pub fn step<S: TimeSource>(&mut self, src: &mut S)
+            -> (OffsetEvent, std::time::Duration) {
+        // The clock may have been restored from a coarse RTC after suspension,
+        // so allow for a larger error, and rapidly re-acquire synchronization:
+        if src.take_suspension().is_some() {
+            self.stats.inflate(self.resume_stddev);
+            self.tick_count = 0;
+            self.resumptions += 1;
         }
+
+        // Server reachability and path delays may have changed,
+        // so re-poll promptly, unless already doing so:
+        if src.take_network_change()
+                && self.tick_count >= OffsetTracker::WARMUP_CYCLES {
+            self.tick_count = 0;
+            self.network_changes += 1;
+        }
+
+        let warmup = self.tick_count < OffsetTracker::WARMUP_CYCLES;
+
+        let tick_time = self.check_precision(src, warmup);
+        self.tick_count += 1;
+
+        let offs = OffsetEvent {
+            avg_offset: self.stats.avg_offset(),
+            stddev_offset: self.stats.stddev_offset(tick_time) };
+
+        let pause = if !warmup {
+            std::time::Duration::from_secs_f32(self.wakeup_interval)
+        } else {
+            std::time::Duration::from_millis(500)
+        };
+
+        ( offs, pause )
     }
 
-    fn check_precision<T>(&mut self, skt: &UdpSocketWrapper,
-                          ctxt: &NtpContext<T>, force_ping: bool) -> Timestamp
-            where T: NtpTimestampGenerator + Copy {
-        let now = utc_now();
+    /// The number of system suspensions detected so far
+    pub fn resumptions(&self) -> u32 {
+        self.resumptions
+    }
+
+    /// The number of network changes which triggered resynchronization
+    pub fn network_changes(&self) -> u32 {
+        self.network_changes
+    }
+
+    /// The current per-server calibration of measurement error against round-trip time
+    pub fn rtt_fits(&self) -> Vec<RttFit> {
+        self.calibrator.fits()
+    }
+
+    fn check_precision<S: TimeSource>(&mut self, src: &mut S,
+                                      force_ping: bool) -> Timestamp {
+        let now = src.now();
 
         // Check if uncertainty in clock-offset is still acceptably small:
         if !force_ping && self.stats.stddev_offset(now) < self.target_precision {
             return now;
         }
 
-        if let Ok(sync) = self.try_ntp_pings(skt, ctxt, 3) {
-            let obs_time = utc_now();
-            self.stats.add_observation(sync.offset as f32 * 1e-6,
-                                       sync.roundtrip as f32 * 0.25e-6 +
-                                            2.0f32.powi(sync.precision as i32),
-                                       obs_time);
-            // Heuristically assume that the offset margin of error
-            // is about a quarter of the round-trip time
+        if let Ok(sync) = self.try_ntp_pings(src, 3) {
+            let obs_time = src.now();
+            // ping.offset() should be *added* to local clock to approximate reference time
+            let offset = sync.offset() as f32;
+            let roundtrip = sync.roundtrip() as f32;
+
+            // Until calibrated, heuristically assume that the offset margin
+            // of error is about a quarter of the round-trip time (by default)
+            let precision = self.calibrator.precision(&sync.server, roundtrip,
+                                                      sync.precision);
+            if self.calibrate_rtt && !force_ping {
+                let (mean, var) = self.stats.predict(obs_time);
+                self.calibrator.add_innovation(&sync.server, roundtrip,
+                                               offset - mean, var);
+            }
+
+            self.stats.add_observation(offset, precision, obs_time);
             obs_time
         } else {
-            utc_now()
+            src.now()
         }
     }
 
-    fn try_ntp_pings<T>(&self, skt: &UdpSocketWrapper, ctxt: &NtpContext<T>,
-                        attempts: u8) -> sntpc::Result<NtpResult>
-            where T: NtpTimestampGenerator + Copy {
+    fn try_ntp_pings<S: TimeSource>(&self, src: &mut S,
+                                    attempts: u8) -> Result<NtpExchange, NtpError> {
         let mut err = None;
 
         for _ in 0 .. attempts {
-            match self.ntp_ping(skt.clone(), ctxt.clone()) {
+            let servers = &self.ntp_servers;
+            let host = &servers[src.choose_server(servers.len())];
+
+            match src.ntp_ping(host) {
                 Ok(ping) => return Ok(ping),
                 Err(e) =>   if err.is_none() {
                                 err = Some(Err(e)) }
@@ -161,15 +392,122 @@ impl OffsetEstimator {
 
         err.expect("Missing failure")
     }
+}
 
-    fn ntp_ping<T>(&self, skt: UdpSocketWrapper,
-                   ctxt: NtpContext<T>) -> sntpc::Result<NtpResult>
-            where T: NtpTimestampGenerator + Copy {
-        // See https://datatracker.ietf.org/doc/html/rfc5905#section-7.3
-        let servers = &self.ntp_servers;
-        let host = &servers[weak_rand() as usize % servers.len()];
-        sntpc::get_time((host.as_str(), 123u16), skt, ctxt)
-        // ping.offset should be *added* to local clock to approximate reference time
+
+pub struct OffsetEstimator {
+    /// Outbound distribution of offset estimates to the Ticker and other subscribers
+    bus: EventBus,
+
+    /// Polling policy and Bayesian model of clock-offset
+    tracker: OffsetTracker,
+
+    /// Optional file to which all NTP exchanges are appended
+    record_path: Option<PathBuf>,
+
+    /// Optional log of polls, offset estimates and resets
+    event_log: Option<EventLog>,
+
+    /// Optional hash-chained record of the clock-offset and its sources
+    audit_log: Option<AuditLog>,
+
+    /// Whether to resynchronize promptly after network changes
+    watch_network: bool,
+
+    /// Flag by which other threads can request that polling finishes
+    stop: Arc<AtomicBool>
+}
+
+impl OffsetEstimator {
+    pub const DEFAULT_TGT_PRECISION: f32 = 0.03;
+    pub const DEFAULT_WAKEUP_ITVL: f32 = 11.0;
+
+    pub fn new(bus: EventBus, config: &SyncConfig,
+               est_config: &EstimatorConfig) -> OffsetEstimator {
+        OffsetEstimator {
+            bus,
+            tracker: OffsetTracker::new(config, est_config),
+            record_path: config.record_path.clone(),
+            event_log: EventLog::from_config(config),
+            audit_log: AuditLog::from_config(config),
+            watch_network: config.watch_network,
+            stop: Arc::new(AtomicBool::new(false))
+        }
+    }
+
+    /// A flag which, once set, causes the polling thread to finish
+    /// within about a second, or once any NTP request in progress completes
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Entry-point for clock-offset thread publishing via the event bus
+    pub fn run(&mut self) {
+        let src = if self.watch_network {
+            SystemSource::with_events(netmon::system_events())
+        } else {
+            SystemSource::new()
+        }.with_stop(self.stop.clone());
+        let src = PublishingSource::new(src, self.bus.clone());
+
+        match self.event_log.clone() {
+            Some(log) => self.run_auditing(LoggingSource::new(src, log)),
+            None =>     self.run_auditing(src)
+        }
+    }
+
+    fn run_auditing<S: TimeSource>(&mut self, src: S) {
+        match self.audit_log.clone() {
+            Some(audit) => self.run_recording(AuditingSource::new(src, audit)),
+            None =>       self.run_recording(src)
+        }
+    }
+
+    fn run_recording<S: TimeSource>(&mut self, src: S) {
+        if let Some(path) = self.record_path.clone() {
+            match std::fs::OpenOptions::new().create(true)
+                                             .append(true).open(&path) {
+                Ok(file) => return self.run_with(RecordingSource::new(src, file)),
+                Err(e) => log::warn!("Failed to open NTP recording {:?} - {:?}",
+                                    path, e)
+            }
+        }
+
+        self.run_with(src)
+    }
+
+    fn run_with<S: TimeSource>(&mut self, mut src: S) {
+        while !self.stop.load(Ordering::Relaxed) {
+            let resets = ( self.tracker.resumptions(), self.tracker.network_changes() );
+            let (offs, pause) = self.tracker.step(&mut src);
+
+            // Resets are logged after the polls of the cycle in which they occurred:
+            if let Some(log) = &self.event_log {
+                let now = src.now();
+                if self.tracker.resumptions() > resets.0 {
+                    log.record(now, LogEvent::Reset { reason: ResetReason::Suspension });
+                }
+                if self.tracker.network_changes() > resets.1 {
+                    log.record(now, LogEvent::Reset { reason: ResetReason::NetworkChange });
+                }
+                log.record(now, LogEvent::Offset(offs));
+            }
+
+            self.bus.publish(UImessage::Offset(offs));
+            self.bus.publish(UImessage::Calibration(self.tracker.rtt_fits()));
+
+            // Wake in time for the next audit record, even if no poll is then needed:
+            let pause = match &self.audit_log {
+                Some(audit) => {
+                    let now = src.now();
+                    audit.record_if_due(now, &offs);
+                    pause.min(audit.until_due(now))
+                },
+                None => pause
+            };
+
+            src.sleep(pause);
+        }
     }
 }
 
@@ -0,0 +1,505 @@
+/*
+ *  ANSI terminal frontend for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{ Seek, SeekFrom, Write },
+    os::fd::{ AsRawFd, FromRawFd, OwnedFd },
+    process::ExitCode,
+    sync::mpsc,
+    time::Duration };
+use crate::{
+    OffsetEvent, PollEvent, TickEvent, TickerMessage, Timestamp, UImessage, utc_now,
+    config::{ DisplayConfig, ECConfig },
+    display::{ self, DigitSuppression, DisplayFormat },
+    ntp::NtpError,
+    stats::{ ExpoAvg, RttFit },
+    workers::{ Signal, Workers, install_signal_handlers, take_signal } };
+
+
+/// The height of the large-digit font, in character rows
+pub const GLYPH_ROWS: usize = 5;
+
+
+/// The block-character rendering of a single character of the time of day
+fn glyph(c: char) -> [&'static str; GLYPH_ROWS] {
+    match c {
+        '0' => [ "███", "█ █", "█ █", "█ █", "███" ],
+        '1' => [ "  █", "  █", "  █", "  █", "  █" ],
+        '2' => [ "███", "  █", "███", "█  ", "███" ],
+        '3' => [ "███", "  █", "███", "  █", "███" ],
+        '4' => [ "█ █", "█ █", "███", "  █", "  █" ],
+        '5' => [ "███", "█  ", "███", "  █", "███" ],
+        '6' => [ "███", "█  ", "███", "█ █", "███" ],
+        '7' => [ "███", "  █", "  █", "  █", "  █" ],
+        '8' => [ "███", "█ █", "███", "█ █", "███" ],
+        '9' => [ "███", "█ █", "███", "  █", "███" ],
+        ':' => [ " ", "█", " ", "█", " " ],
+        '.' => [ " ", " ", " ", " ", "█" ],
+        _ =>   [ "   ", "   ", "   ", "   ", "   " ]
+    }
+}
+
+
+/// Render text as rows of large block characters, with glyphs separated by one column
+pub fn big_text(txt: &str) -> Vec<String> {
+    let mut rows = vec![String::new(); GLYPH_ROWS];
+
+    for (idx, c) in txt.chars().enumerate() {
+        for (row, part) in rows.iter_mut().zip(glyph(c)) {
+            if idx > 0 { row.push(' '); }
+            row.push_str(part);
+        }
+    }
+
+    rows
+}
+
+
+/// One line of terminal output, with its displayed width excluding escape sequences
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Line {
+    text: String,
+    width: usize
+}
+
+impl Line {
+    /// Unstyled text, truncated to a maximum width
+    fn plain(text: &str, max_width: usize) -> Line {
+        let text: String = text.chars().take(max_width).collect();
+        let width = text.chars().count();
+
+        Line { text, width }
+    }
+}
+
+
+/// The recent polling history of one NTP server
+#[derive(Clone, Debug, PartialEq)]
+struct ServerStatus {
+    name: String,
+
+    /// The outcomes of the latest eight requests, with the most recent in the lowest bit,
+    /// following the "reach" register shown by ntpq
+    reach: u8,
+
+    /// The (uncorrected) local time of the latest request
+    last_poll: Option<Timestamp>,
+
+    /// The latest failure, and when it occurred
+    last_error: Option<(NtpError, Timestamp)>
+}
+
+impl ServerStatus {
+    fn new(name: &str) -> ServerStatus {
+        ServerStatus { name: String::from(name), reach: 0, last_poll: None, last_error: None }
+    }
+
+    fn add_poll(&mut self, poll: &PollEvent) {
+        self.reach = (self.reach << 1) | poll.result.is_ok() as u8;
+        self.last_poll = Some(poll.time);
+        if let Err(e) = poll.result {
+            self.last_error = Some(( e, poll.time ));
+        }
+    }
+
+    /// A summary of the server's reachability and calibration, at the given local time
+    fn describe(&self, now: Timestamp, fit: Option<&RttFit>) -> String {
+        let age = |t: Timestamp| (now - t).num_seconds().max(0);
+        let mut txt = match self.last_poll {
+            Some(t) => format!("{}: reach {:03o}, polled {}s ago", self.name, self.reach, age(t)),
+            None => format!("{}: not yet polled", self.name)
+        };
+
+        if let Some((err, t)) = self.last_error {
+            let _ = write!(txt, ", last error: {} ({}s ago)", err, age(t));
+        }
+        if let Some(fit) = fit {
+            let _ = write!(txt, ", {:.3} × RTT + {:.2}ms (n={})",
+                           fit.scale, fit.floor * 1e3, fit.samples);
+        }
+
+        txt
+    }
+}
+
+
+/// Large-digit clock display, rendered as complete frames of ANSI-escaped text
+pub struct TermDisplay {
+    /// The resolution with which the time of day is shown
+    format: DisplayFormat,
+
+    /// The treatment of sub-second digits finer than the clock uncertainty
+    suppression: DigitSuppression,
+
+    /// The latest clock-offset estimate
+    offset: Option<OffsetEvent>,
+
+    /// The latest per-server calibration of measurement error
+    fits: Vec<RttFit>,
+
+    /// The polling history of each server, in order of configuration or first use
+    servers: Vec<ServerStatus>,
+
+    /// The terminal dimensions, as (rows, columns)
+    size: (usize, usize),
+
+    /// The number of lines in the previous frame, or None if the screen must be cleared
+    last_lines: Option<usize>
+}
+
+impl TermDisplay {
+    pub fn new(config: &DisplayConfig, servers: &[String], size: (usize, usize)) -> TermDisplay {
+        TermDisplay {
+            format: config.format,
+            suppression: config.insignificant_digits,
+            offset: None,
+            fits: vec![],
+            servers: servers.iter().map(|s| ServerStatus::new(s)).collect(),
+            size,
+            last_lines: None
+        }
+    }
+
+    /// Adopt new terminal dimensions, forcing the next frame to redraw the whole screen
+    pub fn resize(&mut self, size: (usize, usize)) {
+        self.size = size;
+        self.last_lines = None;
+    }
+
+    pub fn set_offset(&mut self, event: OffsetEvent) {
+        self.offset = Some(event);
+    }
+
+    pub fn set_calibration(&mut self, fits: Vec<RttFit>) {
+        self.fits = fits;
+    }
+
+    pub fn add_poll(&mut self, poll: &PollEvent) {
+        let idx = match self.servers.iter().position(|s| s.name == poll.server) {
+            Some(idx) => idx,
+            None => {
+                self.servers.push(ServerStatus::new(&poll.server));
+                self.servers.len() - 1
+            }
+        };
+
+        self.servers[idx].add_poll(poll);
+    }
+
+    /// The time of day in large digits, or as plain text if the terminal is too narrow
+    fn clock_lines(&self, event: &TickEvent) -> Vec<Line> {
+        let cols = self.size.1;
+        let stddev = self.offset.map_or(f32::INFINITY, |o| o.stddev_offset);
+        let (sig, insig) = self.format.format_split(event.t_nominal, stddev);
+        let insig = if self.suppression == DigitSuppression::Hide { String::new() }
+                    else { insig };
+        let (dim_on, dim_off) = if self.suppression == DigitSuppression::Grey
+                                        && !insig.is_empty()
+                                    { ( "\x1b[2m", "\x1b[22m" ) } else { ( "", "" ) };
+        let phase = display::phase_char(event);
+
+        let sig_rows = big_text(&sig);
+        let insig_rows = big_text(&insig);
+        let sig_width = sig_rows[0].chars().count();
+        let insig_width = if insig.is_empty() { 0 }
+                          else { insig_rows[0].chars().count() + 1 };
+
+        // Allow two further columns for the phase glyph:
+        if sig_width + insig_width + 2 > cols {
+            let width = sig.len() + insig.len() + 2;
+            let text = format!("{}{}{}{} {}", sig, dim_on, insig, dim_off, phase);
+            return vec![ if width <= cols { Line { text, width } }
+                         else { Line::plain(&sig, cols) } ];
+        }
+
+        sig_rows.iter().zip(insig_rows.iter()).enumerate()
+            .map(|(idx, (s, i))| {
+                let sep = if insig.is_empty() { "" } else { " " };
+                let tail = if idx == GLYPH_ROWS - 1 { phase } else { ' ' };
+                Line { text: format!("{}{}{}{}{} {}", s, sep, dim_on, i, dim_off, tail),
+                       width: sig_width + insig_width + 2 }
+            })
+            .collect()
+    }
+
+    /// The clock-offset estimate, and the reachability and calibration of each server,
+    /// at the given (uncorrected) local time
+    fn status_lines(&self, now: Timestamp) -> Vec<Line> {
+        let cols = self.size.1;
+        let offs_txt = match self.offset {
+            Some(offs) => format!("Offset: {:.1}ms ± {:.1}ms",
+                                  offs.avg_offset.num_microseconds().unwrap_or(0) as f64 * 1e-3,
+                                  offs.stddev_offset * 1e3),
+            None => String::from("Offset: awaiting NTP response")
+        };
+        let mut lines = vec![ Line::plain(&offs_txt, cols), Line::default() ];
+
+        for server in self.servers.iter() {
+            let fit = self.fits.iter().find(|f| f.server == server.name);
+            lines.push(Line::plain(&server.describe(now, fit), cols));
+        }
+
+        lines
+    }
+
+    /// The escape sequences and text which draw a complete frame for a clock-tick,
+    /// overwriting the previous frame in place where its layout is unchanged
+    pub fn render(&mut self, event: &TickEvent) -> String {
+        let (rows, cols) = self.size;
+        let mut lines = self.clock_lines(event);
+        lines.push(Line::default());
+        lines.extend(self.status_lines(event.t_transmit));
+        lines.truncate(rows.max(1));
+
+        let mut frame = String::new();
+        if self.last_lines != Some(lines.len()) {
+            frame.push_str("\x1b[2J");
+        }
+
+        let top = rows.saturating_sub(lines.len()) / 2;
+        for (idx, line) in lines.iter().enumerate() {
+            let indent = cols.saturating_sub(line.width) / 2;
+            let _ = write!(frame, "\x1b[{};1H{:indent$}{}\x1b[K",
+                           top + idx + 1, "", line.text, indent = indent);
+        }
+        self.last_lines = Some(lines.len());
+
+        frame
+    }
+}
+
+
+/// The dimensions of the terminal attached to standard output, as (rows, columns)
+fn terminal_size() -> Option<(usize, usize)> {
+    let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+    let rc = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) };
+
+    if rc == 0 && ws.ws_row > 0 && ws.ws_col > 0 {
+        Some(( ws.ws_row as usize, ws.ws_col as usize ))
+    } else {
+        None
+    }
+}
+
+
+/// Standard error, diverted to an anonymous file so that log messages
+/// cannot overwrite the display, and replayed once restored
+struct CapturedStderr {
+    original: OwnedFd,
+    file: File
+}
+
+impl CapturedStderr {
+    /// Divert standard error, provided that it is a terminal
+    fn begin() -> Option<CapturedStderr> {
+        if unsafe { libc::isatty(libc::STDERR_FILENO) } != 1 {
+            return None;
+        }
+
+        let path = std::env::temp_dir().join(format!("eng-clock-stderr-{}", std::process::id()));
+        let file = std::fs::OpenOptions::new().read(true).write(true)
+                                              .create_new(true).open(&path).ok()?;
+        let _ = std::fs::remove_file(&path);
+
+        let original = unsafe { libc::dup(libc::STDERR_FILENO) };
+        if original < 0 {
+            return None;
+        }
+        unsafe { libc::dup2(file.as_raw_fd(), libc::STDERR_FILENO) };
+
+        Some(CapturedStderr { original: unsafe { OwnedFd::from_raw_fd(original) }, file })
+    }
+}
+
+impl Drop for CapturedStderr {
+    fn drop(&mut self) {
+        unsafe { libc::dup2(self.original.as_raw_fd(), libc::STDERR_FILENO) };
+
+        if self.file.seek(SeekFrom::Start(0)).is_ok() {
+            let _ = std::io::copy(&mut self.file, &mut std::io::stderr());
+        }
+    }
+}
+
+
+/// Use of the alternate screen, with hidden cursor, no keyboard echo
+/// and diverted standard error, until dropped
+struct TermSession {
+    saved: Option<libc::termios>,
+    stderr: Option<CapturedStderr>
+}
+
+impl TermSession {
+    fn begin() -> TermSession {
+        let mut attrs: libc::termios = unsafe { std::mem::zeroed() };
+        let saved = if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut attrs) } == 0 {
+            let original = attrs;
+            attrs.c_lflag &= !libc::ECHO;
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &attrs) };
+            Some(original)
+        } else {
+            None
+        };
+
+        print!("\x1b[?1049h\x1b[?25l\x1b[2J");
+        let _ = std::io::stdout().flush();
+
+        TermSession { saved, stderr: CapturedStderr::begin() }
+    }
+}
+
+impl Drop for TermSession {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = std::io::stdout().flush();
+
+        if let Some(attrs) = self.saved {
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &attrs) };
+        }
+
+        // Messages logged while the display was active follow it on the main screen:
+        self.stderr = None;
+    }
+}
+
+
+/// Run the clock within the current terminal until interrupted
+pub fn run(cfg: &ECConfig) -> ExitCode {
+    /// The longest time spent waiting for events before checking for signals
+    const POLL: Duration = Duration::from_millis(200);
+
+    let size = match terminal_size() {
+        Some(size) => size,
+        None => {
+            eprintln!("The terminal frontend requires standard output to be a terminal");
+            return ExitCode::FAILURE;
+        }
+    };
+    install_signal_handlers(&[ Signal::Terminate, Signal::Resize ]);
+
+    let session = TermSession::begin();
+    let workers = Workers::start(cfg);
+
+    let mut screen = TermDisplay::new(&cfg.display, &cfg.sync.ntp_servers, size);
+    let mut avg_latency = ExpoAvg::new(0.1);
+    let stdout = std::io::stdout();
+
+    while !take_signal(Signal::Terminate) {
+        match workers.events.recv_timeout(POLL) {
+            Ok(UImessage::Tick(event)) => {
+                // Output is only written on ticks, so that other events cannot delay them:
+                if take_signal(Signal::Resize) {
+                    if let Some(size) = terminal_size() {
+                        screen.resize(size);
+                    }
+                }
+
+                let frame = screen.render(&event);
+                let mut out = stdout.lock();
+                if out.write_all(frame.as_bytes()).and_then(|_| out.flush()).is_err() {
+                    break;
+                }
+
+                // Latency covers delivery to the terminal, but not any remote connection:
+                let latency = avg_latency.add_duration(utc_now() - event.t_transmit);
+                if display::is_first_in_second(&event) {
+                    let _ = workers.ticker_ctl.send(TickerMessage::Latency(latency));
+                }
+            },
+            Ok(UImessage::Offset(offs)) => screen.set_offset(offs),
+            Ok(UImessage::Calibration(fits)) => screen.set_calibration(fits),
+            Ok(UImessage::Poll(poll)) => screen.add_poll(&poll),
+            Ok(UImessage::Realtime(_)) => {},
+            Err(mpsc::RecvTimeoutError::Timeout) => {},
+            Err(mpsc::RecvTimeoutError::Disconnected) => break
+        }
+    }
+
+    drop(session);
+    workers.stop();
+
+    ExitCode::SUCCESS
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sim::{ Scenario, SimNetwork },
+        sync::TimeSource,
+        testing::* };
+
+    #[test]
+    fn large_digits() {
+        let rows = big_text("12:3");
+
+        assert_eq!(rows.len(), GLYPH_ROWS);
+        assert!(rows.iter().all(|r| r.chars().count() == 3 + 1 + 3 + 1 + 1 + 1 + 3));
+        assert_eq!(rows[0], "  █ ███   ███");
+        assert_eq!(rows[1], "  █   █ █   █");
+        assert!(big_text("").iter().all(|r| r.is_empty()));
+    }
+
+    #[test]
+    fn frame_layout() {
+        let config = DisplayConfig { format: DisplayFormat::Tenths,
+                                     ..DisplayConfig::default() };
+        let mut screen = TermDisplay::new(&config, &[], ( 24, 80 ));
+
+        let first = screen.render(&mk_tick(3723, 250));
+        assert!(first.starts_with("\x1b[2J"));
+        assert!(first.contains("\x1b[2m"));
+        assert!(first.contains("awaiting NTP"));
+        assert!(first.contains(" ."));
+
+        screen.set_offset(OffsetEvent { avg_offset: chrono::Duration::microseconds(1500),
+                                        stddev_offset: 0.01 });
+        let second = screen.render(&mk_tick(3723, 500));
+        assert!(!second.starts_with("\x1b[2J"));
+        assert!(!second.contains("\x1b[2m"));
+        assert!(second.contains("Offset: 1.5ms ± 10.0ms"));
+
+        screen.resize(( 24, 100 ));
+        assert!(screen.render(&mk_tick(3723, 750)).starts_with("\x1b[2J"));
+    }
+
+    #[test]
+    fn server_status() {
+        let servers = [ String::from("good.example"), String::from("idle.example") ];
+        let mut screen = TermDisplay::new(&DisplayConfig::default(), &servers, ( 24, 120 ));
+        let poll = |server: &str, secs, result| PollEvent { server: String::from(server),
+                                                            time: mk_time(secs, (0, 0, 0)),
+                                                            result };
+        let exch = SimNetwork::new(&Scenario::default()).ntp_ping("good.example");
+
+        screen.add_poll(&poll("good.example", 100, Err(NtpError::Timeout)));
+        screen.add_poll(&poll("good.example", 110, exch));
+        screen.add_poll(&poll("extra.example", 115, Err(NtpError::Network)));
+        screen.set_calibration(vec![ RttFit { server: String::from("good.example"),
+                                              scale: 0.25, floor: 5e-4, samples: 12 } ]);
+        let frame = screen.render(&mk_tick(120, 0));
+
+        assert!(frame.contains("good.example: reach 001, polled 10s ago, last error: timed out (20s ago), 0.250 × RTT + 0.50ms (n=12)"));
+        assert!(frame.contains("idle.example: not yet polled"));
+        assert!(frame.contains("extra.example: reach 000, polled 5s ago, last error: network error (5s ago)"));
+        assert!(frame.find("idle.example") < frame.find("extra.example"));
+    }
+
+    #[test]
+    fn narrow_terminal() {
+        let mut screen = TermDisplay::new(&DisplayConfig::default(), &[], ( 2, 12 ));
+        let frame = screen.render(&mk_tick(3723, 0));
+
+        assert!(frame.contains("01:02:03 ="));
+        assert!(!frame.contains('█'));
+        assert!(!frame.contains("Offset"));
+        assert_eq!(frame.matches("\x1b[K").count(), 2);
+    }
+}
+
+// (C)Copyright 2026, RW Penney
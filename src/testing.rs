@@ -2,7 +2,7 @@
 // RW Penney, April 2023
 
 use chrono::{ Duration, TimeZone, Utc };
-use super::Timestamp;
+use super::{ TickEvent, Timestamp };
 
 
 /// Check floating numbers agree to within given absolute tolerance
@@ -24,4 +24,24 @@ pub fn mk_time(seconds: i32, fracs: (i32, i32, i32)) -> Timestamp {
         + Duration::nanoseconds((fracs.2 + 1000 * (fracs.1 + 1000 * fracs.0)) as i64)
 }
 
+
+/// Shorthand for a punctual clock-tick at a given time, with a 250ms period
+pub fn mk_tick(seconds: i32, millis: i32) -> TickEvent {
+    let t_nominal = mk_time(seconds, (millis, 0, 0));
+    let period_us = 250_000;
+
+    TickEvent { t_nominal,
+                t_transmit: t_nominal,
+                tick_id: t_nominal.timestamp_micros() / period_us,
+                period_us,
+                lead: Duration::zero(),
+                wake_error: Duration::zero() }
+}
+
+
+/// A path within the temporary directory, unique to this process
+pub fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("eng-clock-{}-{}", std::process::id(), name))
+}
+
 // (C)Copyright 2023, RW Penney
@@ -5,81 +5,255 @@
 
 use std::sync::mpsc;
 use std::thread;
-use chrono::{ NaiveDateTime, Utc };
-use crate::{ OffsetEvent, TickEvent, Timestamp, UImessage, UIsender, utc_now };
+use std::time::Instant;
+use crate::{
+    TickEvent, TickerMessage, Timestamp, UImessage, utc_now,
+    bus::{ EventBus, EventFilter },
+    config::{ DisplayConfig, TickerConfig, WaitStrategy },
+    rt::{ self, RealtimeRequest } };
+
+
+/// Gradual application of changes in the clock-offset,
+/// so that the corrected time neither jumps backwards nor skips ahead
+#[derive(Clone, Debug)]
+pub struct OffsetSlewer {
+    /// The clock-offset currently being applied
+    applied: chrono::Duration,
+
+    /// The latest estimate of the clock-offset, towards which the applied value moves
+    target: chrono::Duration,
+
+    /// The largest rate of change of the applied offset, as a fraction of elapsed time
+    rate: f64,
+
+    /// The change in offset above which the target is applied immediately
+    step_threshold: chrono::Duration,
+
+    /// The (uncorrected) local time at which the applied offset was last updated,
+    /// or None if no offset has yet been received
+    t_last: Option<Timestamp>
+}
+
+impl OffsetSlewer {
+    pub fn new(config: &TickerConfig) -> OffsetSlewer {
+        OffsetSlewer {
+            applied: chrono::Duration::zero(),
+            target: chrono::Duration::zero(),
+            rate: config.slew_rate as f64,
+            step_threshold: chrono::Duration::milliseconds(config.step_threshold_ms as i64),
+            t_last: None
+        }
+    }
+
+    /// Record a new estimate of the clock-offset, stepping immediately
+    /// if this is the first estimate or it differs greatly from the applied value
+    pub fn set_target(&mut self, offset: chrono::Duration, now: Timestamp) {
+        let applied = self.current(now);
+
+        if self.t_last.is_none() || self.rate <= 0.0
+                || (offset - applied).abs() > self.step_threshold {
+            self.applied = offset;
+        }
+        self.target = offset;
+        self.t_last = Some(now);
+    }
+
+    /// Advance the applied offset towards its target, returning its new value
+    pub fn current(&mut self, now: Timestamp) -> chrono::Duration {
+        if let Some(t_last) = self.t_last {
+            let elapsed_us = (now - t_last).num_microseconds().unwrap_or(i64::MAX).max(0);
+            let max_step = chrono::Duration::microseconds(
+                                (elapsed_us as f64 * self.rate) as i64);
+            let gap = self.target - self.applied;
+
+            self.applied += gap.clamp(-max_step, max_step);
+            self.t_last = Some(now);
+        }
+
+        self.applied
+    }
+}
 
 
 pub struct Ticker {
-    /// Latest estimate of clock offset
-    avg_offset: chrono::Duration,
+    /// Applied estimate of clock offset, possibly lagging the latest estimate
+    slewer: OffsetSlewer,
+
+    /// Latest estimate of display latency, by which ticks are sent early
+    latency: chrono::Duration,
+
+    /// The time-interval between screen updates, in microseconds
+    period_us: i64,
+
+    /// The mechanism used to sleep until the next tick
+    wait_strategy: WaitStrategy,
+
+    /// The interval before each tick spent busy-waiting rather than sleeping
+    spin: chrono::Duration,
 
-    /// Outbound channel for user display
-    ui_channel: UIsender,
+    /// Scheduling settings to be applied to the ticking thread
+    realtime: RealtimeRequest,
+
+    /// Whether the display is visible, such that ticks are needed
+    visible: bool,
+
+    /// Whether the ticking thread should continue
+    running: bool,
+
+    /// Outbound distribution of ticks to the display and other subscribers
+    bus: EventBus,
 
     /// Channel for peered synchronization sources to send updates
-    sync_sender: mpsc::Sender<OffsetEvent>,
+    sync_sender: mpsc::Sender<TickerMessage>,
 
     /// Inbound channel for synchronization updates
-    sync_receiver: mpsc::Receiver<OffsetEvent>
+    sync_receiver: mpsc::Receiver<TickerMessage>
 }
 
 impl Ticker {
-    /// The time-interval between screen updates, in microseconds
-    const PERIOD_US: i64 = 250_000;
+    /// The default time-interval between screen updates, in microseconds
+    pub const DEFAULT_PERIOD_US: i64 = 250_000;
+
+    /// The largest display latency, in microseconds, for which ticks will be sent early
+    const MAX_LATENCY_US: i64 = 50_000;
+
+    /// The interval before each wakeup during which control messages are left queued,
+    /// so that handling them cannot delay the tick
+    const LISTEN_GUARD: std::time::Duration = std::time::Duration::from_millis(2);
 
-    pub fn new(ui_channel: UIsender) -> Ticker {
+    pub fn new(bus: EventBus, config: &DisplayConfig,
+               tkr_config: &TickerConfig) -> Ticker {
         let (sync_sender, sync_receiver) = mpsc::channel();
 
         Ticker {
-            avg_offset: chrono::Duration::minutes(0),
-            ui_channel,
+            slewer: OffsetSlewer::new(tkr_config),
+            latency: chrono::Duration::zero(),
+            period_us: config.tick_period_ms as i64 * 1000,
+            wait_strategy: tkr_config.wait,
+            spin: chrono::Duration::microseconds(tkr_config.spin_us as i64),
+            realtime: tkr_config.realtime_request(),
+            visible: true,
+            running: true,
+            bus,
             sync_sender,
             sync_receiver
         }
     }
 
-    pub fn get_sync(&self) -> mpsc::Sender<OffsetEvent> {
+    pub fn get_sync(&self) -> mpsc::Sender<TickerMessage> {
         self.sync_sender.clone()
     }
 
-    /// Entry-point for tick-generating thread communicating via GLIB messages
+    /// Receive clock-offset estimates published on the event bus
+    pub fn subscribe_offsets(&self) {
+        let sync = self.get_sync();
+
+        self.bus.subscribe(EventFilter::offsets(), move |event: &UImessage| {
+            match event {
+                UImessage::Offset(offs) => sync.send(TickerMessage::Offset(*offs)).is_ok(),
+                _ => true
+            }
+        });
+    }
+
+    /// Entry-point for tick-generating thread publishing via the event bus
     pub fn run(&mut self) {
+        let status = rt::apply(&self.realtime);
+        for warning in status.warnings.iter() {
+            log::warn!("Ticker: {}", warning);
+        }
+        log::info!("Ticker {}", status);
+        self.bus.publish(UImessage::Realtime(status));
 
-        loop {
-            let (t_nominal, tick_id) = self.wait_next();
+        while self.running {
+            let (t_nominal, tick_id, wake_error) = match self.wait_next() {
+                Some(tick) => tick,
+                None => break
+            };
             let t_transmit = utc_now();
 
-            self.ui_channel.send(
-                UImessage::Tick(TickEvent { t_nominal, t_transmit, tick_id })
-            ).unwrap();
+            self.bus.publish(
+                UImessage::Tick(TickEvent { t_nominal, t_transmit, tick_id,
+                                            period_us: self.period_us,
+                                            lead: self.latency,
+                                            wake_error }));
 
-            while let Ok(sync) = self.sync_receiver.try_recv() {
-                self.avg_offset = sync.avg_offset;
+            while let Ok(msg) = self.sync_receiver.try_recv() {
+                self.handle_message(msg);
             }
+
+            // Block while hidden, resuming at the next tick boundary once visible,
+            // noting that our own sync_sender keeps the channel open:
+            while !self.visible && self.running {
+                let msg = self.sync_receiver.recv()
+                              .expect("Ticker channel should remain connected");
+                self.handle_message(msg);
+            }
+        }
+    }
+
+    fn handle_message(&mut self, msg: TickerMessage) {
+        match msg {
+            TickerMessage::Offset(sync) =>
+                self.slewer.set_target(sync.avg_offset, utc_now()),
+            TickerMessage::Latency(latency) =>
+                self.latency = Ticker::clamp_latency(latency),
+            TickerMessage::Visible(visible) =>
+                self.visible = visible,
+            TickerMessage::Stop =>
+                self.running = false
         }
     }
 
-    /// Compute nominal time of next clock update, and sleep until it ready for GUI update
+    /// Compute nominal time of next clock update, and sleep until it ready for GUI update,
+    /// waking early by the display latency so that the update appears on time
+    ///
+    /// Control messages are handled while waiting, with None returned
+    /// as soon as the ticker is asked to stop.
     #[inline]
-    fn wait_next(&self) -> (Timestamp, i64) {
+    fn wait_next(&mut self) -> Option<(Timestamp, i64, chrono::Duration)> {
+        let now = utc_now();
+        let avg_offset = self.slewer.current(now);
         let (t_next_nominal, tick_id, wait) =
-            Ticker::predict_next(utc_now(), self.avg_offset);
+            Ticker::predict_next(now, avg_offset + self.latency,
+                                 self.period_us);
+        let t_wake = now + chrono::Duration::from_std(wait).unwrap();
+        let deadline = Instant::now() + wait;
+        let t_listen = deadline - wait.min(Ticker::LISTEN_GUARD);
+
+        // Our own sync_sender keeps the channel open, so this only ends by timing out:
+        while let Ok(msg) = self.sync_receiver.recv_timeout(
+                                t_listen.saturating_duration_since(Instant::now())) {
+            self.handle_message(msg);
+            if !self.running { return None; }
+        }
+
+        match self.wait_strategy {
+            WaitStrategy::Relative =>
+                thread::sleep(deadline.saturating_duration_since(Instant::now())),
+            WaitStrategy::Absolute =>   sleep_until(t_wake, self.spin)
+        }
 
-        thread::sleep(wait);
+        Some(( t_next_nominal, tick_id, utc_now() - t_wake ))
+    }
 
-        ( t_next_nominal, tick_id )
+    /// Restrict latency compensation to a modest, non-negative, interval
+    fn clamp_latency(latency: chrono::Duration) -> chrono::Duration {
+        let us = latency.num_microseconds().unwrap_or(0);
+        chrono::Duration::microseconds(us.clamp(0, Ticker::MAX_LATENCY_US))
     }
 
+    /// Find the next tick lying at least a quarter-period in the future,
+    /// where ticks are counted in whole periods since the POSIX epoch
     #[inline]
-    fn predict_next(now: Timestamp, avg_offset: chrono::Duration)
+    fn predict_next(now: Timestamp, avg_offset: chrono::Duration, period_us: i64)
             -> (Timestamp, i64, std::time::Duration) {
         let now_us = (now + avg_offset).timestamp_micros();
-        let tick_id = (now_us + Ticker::PERIOD_US + Ticker::PERIOD_US / 4)
-                            / Ticker::PERIOD_US;
-        let step_us = (tick_id * Ticker::PERIOD_US) - now_us;
-        let t_next_nominal = Timestamp::from_utc(
-            NaiveDateTime::from_timestamp_micros(tick_id * Ticker::PERIOD_US)
-                .unwrap(), Utc);
+        let tick_id = (now_us + period_us + period_us / 4) / period_us;
+        let step_us = (tick_id * period_us) - now_us;
+        let t_next_nominal =
+            Timestamp::from_timestamp_micros(tick_id * period_us)
+                .unwrap();
 
         ( t_next_nominal,
           tick_id,
@@ -88,16 +262,55 @@ impl Ticker {
 }
 
 
+/// Sleep until an absolute (uncorrected) time, busy-waiting for the final interval
+pub fn sleep_until(target: Timestamp, spin: chrono::Duration) {
+    let t_sleep = target - spin;
+
+    #[cfg(target_os = "linux")]
+    {
+        let ts = libc::timespec {
+            tv_sec: t_sleep.timestamp() as libc::time_t,
+            tv_nsec: t_sleep.timestamp_subsec_nanos() as libc::c_long };
+
+        // Unlike a relative sleep, this is immune to delays between
+        // computing the target time and entering the system call:
+        loop {
+            let rc = unsafe {
+                libc::clock_nanosleep(libc::CLOCK_REALTIME, libc::TIMER_ABSTIME,
+                                      &ts, std::ptr::null_mut()) };
+            if rc != libc::EINTR { break; }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        if let Ok(wait) = (t_sleep - utc_now()).to_std() {
+            thread::sleep(wait);
+        }
+    }
+
+    while utc_now() < target {
+        std::hint::spin_loop();
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
-    use super::{ Ticker, Timestamp };
+    use super::{ OffsetSlewer, Ticker, Timestamp, sleep_until };
+    use crate::{
+        TickerMessage, utc_now,
+        bus::EventBus,
+        config::{ DisplayConfig, TickerConfig } };
+    use std::time::{ Duration, Instant };
     use crate::testing::*;
 
     #[test]
     fn base_prediction() {
         fn next(s: i32, f: (i32, i32, i32)) -> (Timestamp, i64, u32) {
             let (t_nom, tick, wait) =
-                Ticker::predict_next(mk_time(s, f), chrono::Duration::zero());
+                Ticker::predict_next(mk_time(s, f), chrono::Duration::zero(),
+                                     Ticker::DEFAULT_PERIOD_US);
             ( t_nom, tick % 40, wait.as_micros() as u32 )
         }
 
@@ -114,7 +327,8 @@ mod tests {
         fn next(s: i32, f: (i32, i32, i32), offs_ms: i32) -> (Timestamp, i64, u32) {
             let (t_nom, tick, wait) =
                 Ticker::predict_next(mk_time(s, f),
-                                     chrono::Duration::milliseconds(offs_ms as i64));
+                                     chrono::Duration::milliseconds(offs_ms as i64),
+                                     Ticker::DEFAULT_PERIOD_US);
             ( t_nom, tick % 1000, wait.as_micros() as u32 )
         }
 
@@ -125,6 +339,128 @@ mod tests {
         assert_eq!(next(118, (127, 628, 0), 734),
                    ( mk_time(119, (0, 0, 0)), 476, 138_372));
     }
+
+    #[test]
+    fn latency_lead() {
+        let latency = Ticker::clamp_latency(chrono::Duration::microseconds(1_500));
+        let (t_nom, _, wait) =
+            Ticker::predict_next(mk_time(8, (400, 0, 0)),
+                                 latency,
+                                 Ticker::DEFAULT_PERIOD_US);
+
+        assert_eq!(t_nom, mk_time(8, (500, 0, 0)));
+        assert_eq!(wait.as_micros(), 98_500);
+
+        assert_eq!(Ticker::clamp_latency(chrono::Duration::seconds(-1)),
+                   chrono::Duration::zero());
+        assert_eq!(Ticker::clamp_latency(chrono::Duration::seconds(3)),
+                   chrono::Duration::milliseconds(50));
+    }
+
+    #[test]
+    fn offset_slewing() {
+        let ms = chrono::Duration::milliseconds;
+        let config = TickerConfig { slew_rate: 0.1, step_threshold_ms: 1000,
+                                    ..TickerConfig::default() };
+        let mut slewer = OffsetSlewer::new(&config);
+
+        slewer.set_target(ms(300), mk_time(0, (0, 0, 0)));
+        assert_eq!(slewer.current(mk_time(0, (0, 0, 0))), ms(300));
+
+        slewer.set_target(ms(-200), mk_time(10, (0, 0, 0)));
+        assert_eq!(slewer.current(mk_time(10, (0, 0, 0))), ms(300));
+        assert_eq!(slewer.current(mk_time(11, (0, 0, 0))), ms(200));
+        assert_eq!(slewer.current(mk_time(13, (500, 0, 0))), ms(-50));
+        assert_eq!(slewer.current(mk_time(20, (0, 0, 0))), ms(-200));
+
+        slewer.set_target(ms(1500), mk_time(21, (0, 0, 0)));
+        assert_eq!(slewer.current(mk_time(21, (0, 0, 0))), ms(1500));
+
+        let mut immediate = OffsetSlewer::new(&TickerConfig::default());
+        immediate.set_target(ms(40), mk_time(0, (0, 0, 0)));
+        immediate.set_target(ms(-40), mk_time(1, (0, 0, 0)));
+        assert_eq!(immediate.current(mk_time(1, (0, 0, 0))), ms(-40));
+    }
+
+    #[test]
+    fn monotonic_ticks() {
+        // Corrected time should advance by exactly one tick per wakeup,
+        // even while a large offset change is being slewed:
+        let config = TickerConfig { slew_rate: TickerConfig::MAX_SLEW_RATE,
+                                    ..TickerConfig::default() };
+        let mut slewer = OffsetSlewer::new(&config);
+        let period_us = 100_000;
+        let mut now = mk_time(50, (0, 0, 0));
+        let mut last_id = None;
+
+        slewer.set_target(chrono::Duration::zero(), now);
+        for step in 0 .. 200 {
+            if step == 20 {
+                slewer.set_target(chrono::Duration::milliseconds(-900), now);
+            }
+            let (_, tick_id, wait) =
+                Ticker::predict_next(now, slewer.current(now), period_us);
+            if let Some(last) = last_id {
+                assert_eq!(tick_id, last + 1);
+            }
+            last_id = Some(tick_id);
+            now += chrono::Duration::from_std(wait).unwrap();
+        }
+    }
+
+    #[test]
+    fn absolute_sleep() {
+        for spin_us in [ 0, 500 ] {
+            let target = utc_now() + chrono::Duration::milliseconds(15);
+            sleep_until(target, chrono::Duration::microseconds(spin_us));
+            let late = utc_now() - target;
+
+            assert!(late >= chrono::Duration::zero());
+            assert!(late < chrono::Duration::milliseconds(50));
+        }
+
+        let t0 = utc_now();
+        sleep_until(t0 - chrono::Duration::seconds(1), chrono::Duration::zero());
+        assert!(utc_now() - t0 < chrono::Duration::milliseconds(50));
+    }
+
+    #[test]
+    fn prompt_stop() {
+        let display = DisplayConfig { tick_period_ms: 1000, ..DisplayConfig::default() };
+
+        for visible in [ true, false ] {
+            let mut ticker = Ticker::new(EventBus::new(), &display,
+                                         &TickerConfig::default());
+            let ctl = ticker.get_sync();
+            ctl.send(TickerMessage::Visible(visible)).unwrap();
+            let worker = std::thread::spawn(move || ticker.run());
+
+            std::thread::sleep(Duration::from_millis(50));
+            let t0 = Instant::now();
+            ctl.send(TickerMessage::Stop).unwrap();
+            worker.join().unwrap();
+            assert!(t0.elapsed() < Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn short_periods() {
+        fn next(s: i32, f: (i32, i32, i32), period_ms: i64) -> (Timestamp, i64, u32) {
+            let (t_nom, tick, wait) =
+                Ticker::predict_next(mk_time(s, f), chrono::Duration::zero(),
+                                     period_ms * 1000);
+            ( t_nom, tick % 1000, wait.as_micros() as u32 )
+        }
+
+        assert_eq!(next(0, (0, 0, 0), 10),
+                   ( mk_time(0, (10, 0, 0)), 1, 10_000 ));
+        assert_eq!(next(4, (3, 0, 0), 10),
+                   ( mk_time(4, (10, 0, 0)), 401, 7_000 ));
+        assert_eq!(next(7, (51, 500, 0), 50),
+                   ( mk_time(7, (100, 0, 0)), 142, 48_500 ));
+        assert_eq!(next(9, (975, 0, 0), 100),
+                   ( mk_time(10, (100, 0, 0)), 101, 125_000 ));
+    }
 }
 
 // (C)Copyright 2023, RW Penney
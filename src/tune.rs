@@ -0,0 +1,299 @@
+/*
+ *  Hyperparameter tuning of the clock-offset estimator over recorded observations
+ *  RW Penney, October 2026
+ */
+
+use crate::{
+    Timestamp,
+    config::EstimatorConfig,
+    record::ExchangeRecord,
+    stats::BayesOffset,
+    sync::OffsetTracker };
+
+
+/// A single successful offset measurement extracted from a recording
+#[derive(Clone, Copy, Debug)]
+pub struct Observation {
+    /// The (uncorrected) local time at which the measurement completed
+    pub time: Timestamp,
+
+    /// The correction to be added to the local clock, in seconds
+    pub offset: f32,
+
+    /// The round-trip network delay, in seconds
+    pub roundtrip: f32,
+
+    /// The precision of the server's clock, as log2(seconds)
+    pub precision: i8
+}
+
+impl Observation {
+    /// Extract all successful exchanges from a recording, in time order
+    pub fn from_records(records: &[ExchangeRecord]) -> Vec<Observation> {
+        let mut obs: Vec<Observation> =
+            records.iter()
+                   .filter_map(|r| r.result.as_ref().ok().map(|ex| Observation {
+                        time: r.end,
+                        offset: ex.offset() as f32,
+                        roundtrip: ex.roundtrip() as f32,
+                        precision: ex.precision }))
+                   .collect();
+        obs.sort_by_key(|o| o.time);
+
+        obs
+    }
+}
+
+
+/// A candidate combination of estimator settings
+#[derive(Clone, Debug, PartialEq)]
+pub struct TuneParams {
+    pub estimator: EstimatorConfig,
+
+    /// The desired maximum uncertainty in the clock-offset, in seconds
+    pub target_precision: f32
+}
+
+
+/// Performance of a candidate setting over a sequence of observations
+#[derive(Clone, Debug)]
+pub struct TuneScore {
+    pub params: TuneParams,
+
+    /// The mean log-likelihood of each observation under the estimator's
+    /// prediction made before that observation, in nats
+    pub mean_loglik: f64,
+
+    /// The fraction of observations lying within one predicted standard-deviation
+    pub coverage_1sigma: f64,
+
+    /// The mean squared standardized innovation, which is ideally unity
+    pub mean_sq_z: f64,
+
+    /// The number of observations which would have been polled
+    pub polls: u32,
+
+    /// The rate of polling, per hour of recording
+    pub polls_per_hour: f64,
+
+    /// Overall figure of merit, with larger values being better
+    pub score: f64
+}
+
+
+/// Ranges of settings to be explored in a parameter sweep
+#[derive(Clone, Debug)]
+pub struct TuneGrid {
+    pub prior_stddev: Vec<f32>,
+    pub diffusivity: Vec<f32>,
+    pub rtt_error_factor: Vec<f32>,
+    pub target_precision: Vec<f32>,
+
+    /// The penalty applied to the score for each poll per hour, in nats
+    pub poll_cost: f64
+}
+
+impl Default for TuneGrid {
+    fn default() -> TuneGrid {
+        TuneGrid {
+            prior_stddev: vec![ 1.0, 30.0 ],
+            diffusivity: vec![ 0.05, 0.1, 0.2, 0.5, 1.0, 2.0 ],
+            rtt_error_factor: vec![ 0.1, 0.15, 0.25, 0.4, 0.6 ],
+            target_precision: vec![ 0.01, 0.02, 0.03, 0.05 ],
+            poll_cost: 0.01
+        }
+    }
+}
+
+impl TuneGrid {
+    /// Enumerate every combination of settings within the grid
+    pub fn candidates(&self) -> Vec<TuneParams> {
+        let mut params = vec![];
+
+        for &prior_stddev in self.prior_stddev.iter() {
+            for &diffusivity in self.diffusivity.iter() {
+                for &rtt_error_factor in self.rtt_error_factor.iter() {
+                    for &target_precision in self.target_precision.iter() {
+                        params.push(TuneParams {
+                            estimator: EstimatorConfig {
+                                prior_stddev, diffusivity, rtt_error_factor,
+                                calibrate_rtt: false,
+                                ..EstimatorConfig::default() },
+                            target_precision });
+                    }
+                }
+            }
+        }
+
+        params
+    }
+}
+
+
+/// Score a candidate setting by sequentially predicting each observation,
+/// and incorporating only those which the polling policy would have requested
+///
+/// Observations during the initial warmup are always incorporated
+/// but are not scored, so that all candidates are scored on the same samples.
+/// The round-trip error factor is held fixed, without online calibration.
+pub fn evaluate(obs: &[Observation], params: &TuneParams, poll_cost: f64) -> TuneScore {
+    let est = &params.estimator;
+    let mut bo = BayesOffset::with_diffusivity(est.prior_stddev, est.diffusivity);
+    let (mut polls, mut scored) = (0u32, 0u32);
+    let (mut sum_ll, mut sum_z2, mut within1) = (0.0, 0.0, 0u32);
+
+    for (idx, ob) in obs.iter().enumerate() {
+        let precision = ob.roundtrip * est.rtt_error_factor
+                            + 2.0f32.powi(ob.precision as i32);
+        let warmup = idx < OffsetTracker::WARMUP_CYCLES as usize;
+        let (mean, var) = bo.predict(ob.time);
+
+        if !warmup {
+            let var_pred = var as f64 + (precision as f64).powi(2);
+            let z2 = (ob.offset - mean).powi(2) as f64 / var_pred;
+
+            sum_ll -= 0.5 * ((2.0 * std::f64::consts::PI * var_pred).ln() + z2);
+            sum_z2 += z2;
+            if z2 <= 1.0 { within1 += 1; }
+            scored += 1;
+        }
+
+        if warmup || var.sqrt() >= params.target_precision {
+            bo.add_observation(ob.offset, precision, ob.time);
+            polls += 1;
+        }
+    }
+
+    let hours = match (obs.first(), obs.last()) {
+        (Some(a), Some(b)) =>
+            ((b.time - a.time).num_milliseconds() as f64 / 3.6e6).max(1.0 / 60.0),
+        _ => 1.0
+    };
+    let n = (scored as f64).max(1.0);
+    let mean_loglik = sum_ll / n;
+    let polls_per_hour = polls as f64 / hours;
+
+    TuneScore {
+        params: params.clone(),
+        mean_loglik,
+        coverage_1sigma: within1 as f64 / n,
+        mean_sq_z: sum_z2 / n,
+        polls,
+        polls_per_hour,
+        score: mean_loglik - poll_cost * polls_per_hour
+    }
+}
+
+
+/// Evaluate every candidate within a grid, returning results best-first
+pub fn sweep(obs: &[Observation], grid: &TuneGrid) -> Vec<TuneScore> {
+    let mut scores: Vec<TuneScore> =
+        grid.candidates().iter()
+            .map(|p| evaluate(obs, p, grid.poll_cost))
+            .collect();
+    scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    scores
+}
+
+
+/// Render a ranked table of scores
+pub fn format_table(scores: &[TuneScore]) -> String {
+    let mut table = String::from(
+        "rank   prior  diffus  rtt_fac  target   loglik  cover1  mean_z2  polls/h   score\n");
+
+    for (rank, sc) in scores.iter().enumerate() {
+        let est = &sc.params.estimator;
+        table += &format!("{:4}  {:6.1}  {:6.3}  {:7.3}  {:6.3}  {:7.3}  {:5.1}%  {:7.3}  {:7.2}  {:6.3}\n",
+                          rank + 1, est.prior_stddev, est.diffusivity,
+                          est.rtt_error_factor, sc.params.target_precision,
+                          sc.mean_loglik, sc.coverage_1sigma * 1e2,
+                          sc.mean_sq_z, sc.polls_per_hour, sc.score);
+    }
+
+    table
+}
+
+
+/// Render the winning settings as a TOML configuration snippet
+pub fn format_toml(best: &TuneScore) -> String {
+    format!("[sync]\ntarget_precision = {:?}\n\n{}",
+            best.params.target_precision, best.params.estimator.to_toml())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    /// Synthesize observations with known noise level and no drift
+    fn noisy_obs(n: usize, noise: f32) -> Vec<Observation> {
+        let mut rng = crate::sim::SimRng::new(11);
+
+        (0 .. n).map(|i| Observation {
+                    time: mk_time(60 * i as i32, (0, 0, 0)),
+                    offset: 0.2 + noise * rng.gaussian() as f32,
+                    roundtrip: 4.0 * noise,
+                    precision: -20 })
+                .collect()
+    }
+
+    #[test]
+    fn grid_size() {
+        let grid = TuneGrid::default();
+
+        assert_eq!(grid.candidates().len(),
+                   grid.prior_stddev.len() * grid.diffusivity.len()
+                   * grid.rtt_error_factor.len() * grid.target_precision.len());
+    }
+
+    #[test]
+    fn calibrated_scoring() {
+        let obs = noisy_obs(500, 0.01);
+        let params = |rtt_error_factor| TuneParams {
+            estimator: EstimatorConfig { rtt_error_factor, diffusivity: 0.01,
+                                         ..EstimatorConfig::default() },
+            target_precision: 0.0 };
+
+        let good = evaluate(&obs, &params(0.25), 0.0);
+        assert_eq!(good.polls, 500);
+        assert_close(good.mean_sq_z, 1.0, 0.2);
+        assert_close(good.coverage_1sigma, 0.683, 0.08);
+
+        let narrow = evaluate(&obs, &params(0.05), 0.0);
+        assert!(narrow.mean_sq_z > 5.0);
+        assert!(narrow.mean_loglik < good.mean_loglik);
+    }
+
+    #[test]
+    fn poll_counting() {
+        let obs = noisy_obs(200, 0.01);
+        let mut params = TuneParams { estimator: EstimatorConfig::default(),
+                                      target_precision: 0.0 };
+        let all = evaluate(&obs, &params, 0.0);
+
+        params.target_precision = 0.03;
+        let few = evaluate(&obs, &params, 0.0);
+
+        assert!(few.polls < all.polls / 4);
+        assert!(few.polls >= OffsetTracker::WARMUP_CYCLES);
+    }
+
+    #[test]
+    fn ranking() {
+        let obs = noisy_obs(300, 0.005);
+        let scores = sweep(&obs, &TuneGrid::default());
+
+        assert!(scores.windows(2).all(|w| w[0].score >= w[1].score));
+        assert!(format_table(&scores[..3]).lines().count() == 4);
+
+        let snippet: toml::Value = toml::from_str(&format_toml(&scores[0])).unwrap();
+        assert_eq!(snippet["sync"]["target_precision"].as_float().map(|x| x as f32),
+                   Some(scores[0].params.target_precision));
+        let est: EstimatorConfig = snippet["estimator"].clone().try_into().unwrap();
+        assert_eq!(est, scores[0].params.estimator);
+    }
+}
+
+// (C)Copyright 2026, RW Penney
@@ -0,0 +1,128 @@
+/*
+ *  Clock threads and process signals shared by headless frontends for eng-clock
+ *  RW Penney, October 2026
+ */
+
+use std::{
+    sync::{ Arc, mpsc, atomic::{ AtomicBool, Ordering } },
+    thread };
+use crate::{
+    TickerMessage, UImessage,
+    bus::{ EventBus, EventFilter },
+    config::ECConfig,
+    sync::OffsetEstimator,
+    ticker::Ticker };
+
+
+/// The asynchronous requests which a frontend may act upon
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    /// SIGTERM or SIGINT
+    Terminate = 0,
+
+    /// SIGHUP
+    Reload = 1,
+
+    /// SIGWINCH
+    Resize = 2
+}
+
+impl Signal {
+    fn signums(self) -> &'static [libc::c_int] {
+        match self {
+            Signal::Terminate =>    &[ libc::SIGTERM, libc::SIGINT ],
+            Signal::Reload =>       &[ libc::SIGHUP ],
+            Signal::Resize =>       &[ libc::SIGWINCH ]
+        }
+    }
+}
+
+
+/// Flags set asynchronously by signal handlers, indexed by Signal
+static RECEIVED: [AtomicBool; 3] = [ AtomicBool::new(false),
+                                     AtomicBool::new(false),
+                                     AtomicBool::new(false) ];
+
+extern "C" fn on_signal(signum: libc::c_int) {
+    let signal = match signum {
+        libc::SIGHUP =>     Signal::Reload,
+        libc::SIGWINCH =>   Signal::Resize,
+        _ =>                Signal::Terminate
+    };
+    RECEIVED[signal as usize].store(true, Ordering::SeqCst);
+}
+
+/// Catch the process signals corresponding to the given requests
+pub fn install_signal_handlers(signals: &[Signal]) {
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+
+    for signum in signals.iter().flat_map(|s| s.signums()) {
+        unsafe { libc::signal(*signum, handler) };
+    }
+}
+
+/// Whether the given request has arrived since last checked
+pub fn take_signal(signal: Signal) -> bool {
+    RECEIVED[signal as usize].swap(false, Ordering::SeqCst)
+}
+
+
+/// A running Ticker and OffsetEstimator, built from one version of the configuration
+pub struct Workers {
+    /// All events published by the two threads
+    pub events: mpsc::Receiver<UImessage>,
+
+    pub ticker_ctl: mpsc::Sender<TickerMessage>,
+    estimator_stop: Arc<AtomicBool>,
+    threads: [ thread::JoinHandle<()>; 2 ]
+}
+
+impl Workers {
+    pub fn start(cfg: &ECConfig) -> Workers {
+        let bus = EventBus::new();
+        let events = bus.subscribe_channel(EventFilter::all());
+
+        let mut ticker = Ticker::new(bus.clone(), &cfg.display, &cfg.ticker);
+        ticker.subscribe_offsets();
+        let ticker_ctl = ticker.get_sync();
+        let mut offest = OffsetEstimator::new(bus, &cfg.sync, &cfg.estimator);
+        let estimator_stop = offest.stop_handle();
+
+        let threads = [ thread::spawn(move || { ticker.run() }),
+                        thread::spawn(move || { offest.run() }) ];
+
+        Workers { events, ticker_ctl, estimator_stop, threads }
+    }
+
+    /// Ask both threads to finish, and wait until they have done so,
+    /// such that their successors never overlap with them
+    pub fn stop(self) {
+        let _ = self.ticker_ctl.send(TickerMessage::Stop);
+        self.estimator_stop.store(true, Ordering::Relaxed);
+
+        for handle in self.threads {
+            if handle.join().is_err() {
+                log::error!("Clock thread panicked while stopping");
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signal_flags() {
+        install_signal_handlers(&[ Signal::Resize ]);
+        assert!(!take_signal(Signal::Resize));
+
+        unsafe { libc::raise(libc::SIGWINCH) };
+        assert!(take_signal(Signal::Resize));
+        assert!(!take_signal(Signal::Resize));
+        assert!(!take_signal(Signal::Terminate));
+    }
+}
+
+// (C)Copyright 2026, RW Penney